@@ -4,5 +4,7 @@ mod cube_direction;
 mod offset;
 
 pub use axial::Axial;
+pub(crate) use axial::SIZE;
 pub use cube::Cube;
+pub use cube_direction::Direction;
 pub use offset::Offset;
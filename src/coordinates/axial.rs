@@ -4,7 +4,11 @@ use super::cube::Cube;
 use super::offset::Offset;
 use bevy::prelude::Vec3;
 
+/// Distance from the centre of a hex to its corners in world units.
+pub(crate) const SIZE: f32 = 0.5;
+
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Axial {
     pub q: i32,
     pub r: i32,
@@ -49,7 +53,6 @@ impl From<Cube> for Axial {
 
 impl From<Axial> for Vec3 {
     fn from(axial: Axial) -> Self {
-        const SIZE: f32 = 0.5;
         let sqrt_3 = f32::sqrt(3.);
         let (q, r) = (axial.q as f32, axial.r as f32);
         let x = SIZE * (sqrt_3 * q + 0.5 * sqrt_3 * r);
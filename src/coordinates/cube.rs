@@ -1,6 +1,10 @@
 use std::fmt::Display;
 
-use super::{axial::Axial, offset::Offset};
+use super::{
+    axial::{Axial, SIZE},
+    offset::Offset,
+};
+use bevy::prelude::Vec3;
 
 use thiserror::Error;
 
@@ -13,12 +17,35 @@ pub enum CoordinateError {
 }
 
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "CubeFields")
+)]
 pub struct Cube {
     pub q: i32,
     pub r: i32,
     pub s: i32,
 }
 
+/// Unchecked fields of a deserialised `Cube`, rejected unless they sum up to zero.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct CubeFields {
+    q: i32,
+    r: i32,
+    s: i32,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<CubeFields> for Cube {
+    type Error = CoordinateError;
+
+    fn try_from(fields: CubeFields) -> Result<Self, Self::Error> {
+        Cube::new(fields.q, fields.r, fields.s)
+    }
+}
+
 impl Cube {
     pub fn origin() -> Self {
         Self { q: 0, r: 0, s: 0 }
@@ -78,6 +105,67 @@ impl Cube {
         use strum::IntoEnumIterator;
         Direction::iter().map(move |d| self + Cube::from(d))
     }
+
+    /// All hexes at most `radius` steps away, including this one.
+    pub fn range(self, radius: u32) -> impl Iterator<Item = Cube> {
+        let radius = radius as i32;
+
+        (-radius..=radius).flat_map(move |q| {
+            let rs = (-radius).max(-q - radius)..=radius.min(-q + radius);
+            rs.map(move |r| self + Cube { q, r, s: -q - r })
+        })
+    }
+
+    /// Hexes on the straight line to `other`, including both ends.
+    pub fn line_to(self, other: Cube) -> Vec<Cube> {
+        let steps = self.distance_to(other);
+        if steps == 0 {
+            return vec![self];
+        }
+
+        // Nudging the line keeps it from running exactly along hex borders, where rounding
+        // would pick sides inconsistently.
+        let lerp = |a: i32, b: i32, t: f32| a as f32 + (b - a) as f32 * t;
+        (0..=steps)
+            .map(|step| {
+                let t = step as f32 / steps as f32;
+                Cube::round(
+                    lerp(self.q, other.q, t) + 1e-6,
+                    lerp(self.r, other.r, t) + 2e-6,
+                )
+            })
+            .collect()
+    }
+
+    /// Hex containing the fractional coordinate `(q, r)`.
+    pub fn round(q: f32, r: f32) -> Self {
+        let s = -q - r;
+        let (mut round_q, mut round_r, round_s) = (q.round(), r.round(), s.round());
+
+        // Rounding each component on its own can break q + r + s == 0, so the one that moved
+        // the most is recomputed from the other two.
+        let (diff_q, diff_r, diff_s) = (
+            (round_q - q).abs(),
+            (round_r - r).abs(),
+            (round_s - s).abs(),
+        );
+        if diff_q > diff_r && diff_q > diff_s {
+            round_q = -round_r - round_s;
+        } else if diff_r > diff_s {
+            round_r = -round_q - round_s;
+        }
+
+        let (q, r) = (round_q as i32, round_r as i32);
+        Cube { q, r, s: -q - r }
+    }
+
+    /// Hex at a world position, ignoring the height. Inverse of the `Axial` to `Vec3` conversion.
+    pub fn from_world(position: Vec3) -> Self {
+        let r = position.z / (1.5 * SIZE);
+        let q = position.x / (f32::sqrt(3.0) * SIZE) - 0.5 * r;
+
+        Cube::round(q, r)
+    }
 }
 
 impl From<Axial> for Cube {
@@ -111,6 +199,15 @@ mod tests {
         assert_eq!(coord, Ok(Cube { q: 0, r: 0, s: 0 }));
     }
 
+    #[cfg(feature = "serde")]
+    #[test]
+    fn deserialising_checks_coordinate_sum() {
+        let cube: Cube = serde_json::from_str(r#"{"q":1,"r":2,"s":-3}"#).unwrap();
+        assert_eq!(cube, Cube::new(1, 2, -3).unwrap());
+
+        assert!(serde_json::from_str::<Cube>(r#"{"q":1,"r":2,"s":3}"#).is_err());
+    }
+
     #[test]
     fn creating_invalid_coordinate_fails() {
         let coord = Cube::new(1, 0, 0);
@@ -168,4 +265,48 @@ mod tests {
         assert!(origin_neighbours.contains(&Cube::new(-1, 1, 0).unwrap()));
         assert!(origin_neighbours.contains(&Cube::new(0, 1, -1).unwrap()));
     }
+
+    #[test]
+    fn range_works() {
+        let centre = Cube::new(2, -1, -1).unwrap();
+
+        assert_eq!(centre.range(0).collect::<Vec<_>>(), vec![centre]);
+        assert_eq!(centre.range(1).count(), 7);
+        assert_eq!(centre.range(3).count(), 37);
+        assert!(centre.range(3).all(|cube| cube.distance_to(centre) <= 3));
+    }
+
+    #[test]
+    fn line_works() {
+        let end = Cube::new(3, -1, -2).unwrap();
+
+        let line = Cube::origin().line_to(end);
+
+        assert_eq!(line.len(), 4);
+        assert_eq!(line.first(), Some(&Cube::origin()));
+        assert_eq!(line.last(), Some(&end));
+        assert!(line
+            .windows(2)
+            .all(|pair| pair[0].distance_to(pair[1]) == 1));
+        assert_eq!(Cube::origin().line_to(Cube::origin()), vec![Cube::origin()]);
+    }
+
+    #[test]
+    fn round_works() {
+        assert_eq!(Cube::round(0.1, -0.2), Cube::origin());
+        assert_eq!(Cube::round(0.9, -0.1), Cube::new(1, 0, -1).unwrap());
+        // s = -1.4 rounds to -1, which does not fit q and r rounded to 1.
+        assert_eq!(Cube::round(0.7, 0.7), Cube::new(1, 1, -2).unwrap());
+        assert_eq!(Cube::round(0.1, 0.8), Cube::new(0, 1, -1).unwrap());
+    }
+
+    #[test]
+    fn from_world_inverts_layout() {
+        for cube in Cube::origin().neighbours().flat_map(|c| c.neighbours()) {
+            let centre = Vec3::from(Axial::from(cube));
+
+            assert_eq!(Cube::from_world(centre), cube);
+            assert_eq!(Cube::from_world(centre + Vec3::new(0.2, 5.0, -0.2)), cube);
+        }
+    }
 }
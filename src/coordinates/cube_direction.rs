@@ -2,7 +2,8 @@ use strum::EnumIter;
 
 use super::Cube;
 
-#[derive(Debug, PartialEq, Eq, Hash, EnumIter)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, EnumIter)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Direction {
     E,
     NE,
@@ -12,6 +13,32 @@ pub enum Direction {
     SE,
 }
 
+impl Direction {
+    /// Index of the hex side facing this direction. Side `n` faces the angle `60° * n` in the
+    /// XZ plane, measured from +X towards +Z, which matches the world layout of `Axial`.
+    pub fn side_index(self) -> u8 {
+        match self {
+            Direction::E => 0,
+            Direction::SE => 1,
+            Direction::SW => 2,
+            Direction::W => 3,
+            Direction::NW => 4,
+            Direction::NE => 5,
+        }
+    }
+
+    pub fn from_side_index(side: u8) -> Direction {
+        match side % 6 {
+            0 => Direction::E,
+            1 => Direction::SE,
+            2 => Direction::SW,
+            3 => Direction::W,
+            4 => Direction::NW,
+            _ => Direction::NE,
+        }
+    }
+}
+
 impl From<Direction> for Cube {
     fn from(dir: Direction) -> Self {
         match dir {
@@ -32,3 +59,34 @@ impl std::ops::Add<Direction> for Cube {
         self + Cube::from(rhs)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::Vec3;
+    use strum::IntoEnumIterator;
+
+    use super::*;
+    use crate::coordinates::Axial;
+
+    #[test]
+    fn side_index_roundtrip_works() {
+        for direction in Direction::iter() {
+            assert_eq!(
+                Direction::from_side_index(direction.side_index()),
+                direction
+            );
+        }
+    }
+
+    #[test]
+    fn side_index_matches_world_layout() {
+        for direction in Direction::iter() {
+            let angle = (60.0 * direction.side_index() as f32).to_radians();
+            let expected = Vec3::new(angle.cos(), 0.0, angle.sin());
+
+            let position = Vec3::from(Axial::from(Cube::from(direction)));
+
+            assert!(position.normalize().abs_diff_eq(expected, 1e-6));
+        }
+    }
+}
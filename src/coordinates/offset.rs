@@ -3,6 +3,7 @@ use std::fmt::Display;
 use bevy::prelude::IVec2;
 
 #[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Offset {
     pub col: i32,
     pub row: i32,
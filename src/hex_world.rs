@@ -1,3 +1,38 @@
+mod editor;
+mod events;
+mod heightmap;
 mod hex_world;
+mod history;
+mod hover;
+mod index;
+mod lod;
+mod map;
+mod map_file;
+mod picking;
+mod save;
+mod selection;
+mod settings;
+mod tiled;
+mod tiles;
 
-pub use hex_world::HexWorld;
+pub use editor::MapEditor;
+pub use events::{
+    HexClicked, HexDrag, HexDragEnd, HexDragStart, HexHoverEnter, HexHoverExit, HoveredHex,
+};
+pub use heightmap::{
+    image_from_map, load_heightmap, save_heightmap, tiles_from_image, HeightmapSettings,
+};
+pub use hex_world::{Energy, HexTileBundle, HexWorld, HexWorldSystem, Hexagon};
+pub use history::{EditHistory, TileEdit, Transaction};
+pub use hover::HexTooltipSettings;
+pub use index::HexEntityIndex;
+pub use map::{Brush, EditAction, HexMap, TileData};
+pub use map_file::{MapFile, MapFileError, MapFormat, MapMetadata, TileRecord, MAP_VERSION};
+pub use save::{ExportHeightmap, ImportHeightmap, ImportTiled, LoadMap, SaveMap};
+pub use selection::{HexSelection, HexSelectionChanged, SelectionMode, SelectionTool};
+pub use settings::{HexWorldSettings, MapShape, WaveSettings};
+pub use tiled::{
+    load_tiled, tiled_to_axial, StaggerAxis, StaggerIndex, TiledError, TiledImportSettings,
+    TiledLayer, TiledMap,
+};
+pub use tiles::TileMaterials;
@@ -0,0 +1,199 @@
+use std::{collections::HashSet, path::PathBuf};
+
+use bevy::prelude::*;
+
+use crate::{
+    coordinates::Cube,
+    input::cursor_ray::{CursorRay, PickingRay},
+};
+
+use super::events::HoveredHex;
+use super::history::EditHistory;
+use super::map::{Brush, EditAction, HexMap, TileData};
+use super::save::{ExportHeightmap, ImportHeightmap, LoadMap, SaveMap};
+use super::settings::HexWorldSettings;
+
+/// Elevation change of a single raise or lower step.
+const ELEVATION_STEP: f32 = 0.25;
+
+/// Radius of the brush picked with the 2 key.
+const BRUSH_RADIUS: u32 = 2;
+
+/// In-game map editor, painting its `action` on the tiles under the cursor with its `brush`
+/// while the left mouse button is held.
+///
+/// F2 toggles it. While it is enabled 1, 2 and 3 pick the single hex, radius and line brush, R
+/// and F raise and lower tiles, K cycles through the tile kinds, T adds and X removes tiles.
+/// Control and Z undoes a stroke, control and Y or control, shift and Z redoes it. Control and S
+/// saves the map to `file`, control and O loads it from there. Control and E exports the
+/// heightmap next to it as PNG, control and I imports it.
+pub struct MapEditor {
+    pub enabled: bool,
+    pub brush: Brush,
+    pub action: EditAction,
+    pub file: PathBuf,
+}
+
+impl Default for MapEditor {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            brush: Brush::Single,
+            action: EditAction::Raise(ELEVATION_STEP),
+            file: "maps/map.hexmap".into(),
+        }
+    }
+}
+
+/// Hexes touched by the stroke of the mouse button currently held.
+#[derive(Default)]
+pub(super) struct BrushStroke {
+    start: Option<Cube>,
+    painted: HashSet<Cube>,
+}
+
+/// Hex at which `ray` hits the ground plane, for placing tiles where there are none.
+fn ground_hex(ray: PickingRay) -> Option<Cube> {
+    if ray.direction.y >= 0.0 || ray.origin.y <= 0.0 {
+        return None;
+    }
+
+    let distance = -ray.origin.y / ray.direction.y;
+    Some(Cube::from_world(ray.origin + distance * ray.direction))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(super) fn editor_keys(
+    keys: Res<Input<KeyCode>>,
+    settings: Res<HexWorldSettings>,
+    mut editor: ResMut<MapEditor>,
+    mut history: ResMut<EditHistory>,
+    mut map: ResMut<HexMap>,
+    mut save: EventWriter<SaveMap>,
+    mut load: EventWriter<LoadMap>,
+    mut export_heightmap: EventWriter<ExportHeightmap>,
+    mut import_heightmap: EventWriter<ImportHeightmap>,
+) {
+    if keys.just_pressed(KeyCode::F2) {
+        editor.enabled = !editor.enabled;
+        info!("Map editor {}", if editor.enabled { "on" } else { "off" });
+    }
+
+    if !editor.enabled {
+        return;
+    }
+
+    if keys.any_pressed([KeyCode::LControl, KeyCode::RControl]) {
+        let shift = keys.any_pressed([KeyCode::LShift, KeyCode::RShift]);
+        if keys.just_pressed(KeyCode::Y) || (shift && keys.just_pressed(KeyCode::Z)) {
+            history.redo(&mut map);
+        } else if keys.just_pressed(KeyCode::Z) {
+            history.undo(&mut map);
+        } else if keys.just_pressed(KeyCode::S) {
+            save.send(SaveMap(editor.file.clone()));
+        } else if keys.just_pressed(KeyCode::O) {
+            load.send(LoadMap(editor.file.clone()));
+        } else if keys.just_pressed(KeyCode::E) {
+            export_heightmap.send(ExportHeightmap(editor.file.with_extension("png")));
+        } else if keys.just_pressed(KeyCode::I) {
+            import_heightmap.send(ImportHeightmap(editor.file.with_extension("png")));
+        }
+        return;
+    }
+
+    for key in keys.get_just_pressed() {
+        match key {
+            KeyCode::Key1 => editor.brush = Brush::Single,
+            KeyCode::Key2 => editor.brush = Brush::Radius(BRUSH_RADIUS),
+            KeyCode::Key3 => editor.brush = Brush::Line,
+            KeyCode::R => editor.action = EditAction::Raise(ELEVATION_STEP),
+            KeyCode::F => editor.action = EditAction::Raise(-ELEVATION_STEP),
+            KeyCode::T => editor.action = EditAction::Add(TileData::default()),
+            KeyCode::X => editor.action = EditAction::Remove,
+            KeyCode::K => {
+                let kinds = settings.tile_colors.len().max(1) as u32;
+                editor.action = match editor.action {
+                    EditAction::SetKind(kind) => EditAction::SetKind((kind + 1) % kinds),
+                    _ => EditAction::SetKind(0),
+                };
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Paints the editor action on the map. A stroke edits every hex at most once, so holding the
+/// button over a tile does not keep raising it. The line brush paints when the button is
+/// released. Every stroke is one transaction in the `EditHistory`.
+pub(super) fn edit_map(
+    editor: Res<MapEditor>,
+    buttons: Res<Input<MouseButton>>,
+    cursor_ray: Res<CursorRay>,
+    hovered: Res<HoveredHex>,
+    mut map: ResMut<HexMap>,
+    mut history: ResMut<EditHistory>,
+    mut stroke: Local<BrushStroke>,
+) {
+    if !editor.enabled {
+        return;
+    }
+
+    // New tiles go where the cursor meets the ground, all other edits need an existing tile.
+    let target = match editor.action {
+        EditAction::Add(_) => cursor_ray.0.and_then(ground_hex),
+        _ => hovered.0.map(|(cube, _)| cube),
+    };
+
+    if buttons.just_pressed(MouseButton::Left) {
+        *stroke = BrushStroke::default();
+        history.begin();
+    }
+
+    if let Some(target) = target.filter(|_| buttons.pressed(MouseButton::Left)) {
+        let start = *stroke.start.get_or_insert(target);
+
+        if editor.brush != Brush::Line {
+            for cube in editor.brush.footprint(start, target) {
+                if stroke.painted.insert(cube) {
+                    history.apply(&mut map, cube, &editor.action);
+                }
+            }
+        }
+    }
+
+    if buttons.just_released(MouseButton::Left) {
+        if let (Brush::Line, Some(start), Some(target)) = (editor.brush, stroke.start, target) {
+            for cube in editor.brush.footprint(start, target) {
+                history.apply(&mut map, cube, &editor.action);
+            }
+        }
+        *stroke = BrushStroke::default();
+        history.commit();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ground_hex_is_below_the_cursor() {
+        let ray = PickingRay {
+            origin: Vec3::new(0.0, 10.0, 0.0),
+            direction: Vec3::new(0.0, -1.0, 0.0),
+        };
+        assert_eq!(ground_hex(ray), Some(Cube::origin()));
+
+        let ray = PickingRay {
+            origin: Vec3::new(0.0, 1.0, 0.0),
+            direction: Vec3::new(1.0, -1.0, 0.0).normalize(),
+        };
+        assert_eq!(ground_hex(ray), Some(Cube::from_world(Vec3::X)));
+
+        let upwards = PickingRay {
+            origin: Vec3::new(0.0, 1.0, 0.0),
+            direction: Vec3::Y,
+        };
+        assert_eq!(ground_hex(upwards), None);
+    }
+}
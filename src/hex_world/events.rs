@@ -0,0 +1,298 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::coordinates::Cube;
+
+/// A mouse button was pressed and released over the same tile without dragging in between.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HexClicked {
+    pub cube: Cube,
+    pub button: MouseButton,
+    pub entity: Entity,
+}
+
+/// The cursor moved onto a tile.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HexHoverEnter {
+    pub cube: Cube,
+    pub entity: Entity,
+}
+
+/// The cursor left a tile, either onto another one or off the map.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HexHoverExit {
+    pub cube: Cube,
+    pub entity: Entity,
+}
+
+/// The cursor left the tile a mouse button was pressed on while holding the button.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HexDragStart {
+    pub cube: Cube,
+    pub button: MouseButton,
+    pub entity: Entity,
+}
+
+/// The cursor moved onto another tile during a drag.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HexDrag {
+    /// Tile the drag started on.
+    pub start: Cube,
+    pub cube: Cube,
+    pub button: MouseButton,
+    pub entity: Entity,
+}
+
+/// The mouse button of a drag was released.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HexDragEnd {
+    /// Tile the drag started on.
+    pub start: Cube,
+    /// Tile the button was released over, `None` if the cursor was off the map.
+    pub end: Option<Cube>,
+    pub button: MouseButton,
+}
+
+/// Tile currently under the cursor.
+#[derive(Default)]
+pub struct HoveredHex(pub Option<(Cube, Entity)>);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum PointerEvent {
+    Clicked(HexClicked),
+    HoverEnter(HexHoverEnter),
+    HoverExit(HexHoverExit),
+    DragStart(HexDragStart),
+    Drag(HexDrag),
+    DragEnd(HexDragEnd),
+}
+
+struct Press {
+    start: (Cube, Entity),
+    last: Cube,
+    dragging: bool,
+}
+
+/// Tile under the cursor and the tiles mouse buttons were pressed on, from which the events are
+/// derived every frame.
+#[derive(Default)]
+pub(super) struct PointerState {
+    hovered: Option<(Cube, Entity)>,
+    presses: HashMap<MouseButton, Press>,
+}
+
+impl PointerState {
+    fn update(
+        &mut self,
+        hovered: Option<(Cube, Entity)>,
+        just_pressed: impl Iterator<Item = MouseButton>,
+        just_released: impl Iterator<Item = MouseButton>,
+    ) -> Vec<PointerEvent> {
+        let mut events = vec![];
+
+        if hovered != self.hovered {
+            if let Some((cube, entity)) = self.hovered {
+                events.push(PointerEvent::HoverExit(HexHoverExit { cube, entity }));
+            }
+            if let Some((cube, entity)) = hovered {
+                events.push(PointerEvent::HoverEnter(HexHoverEnter { cube, entity }));
+            }
+            self.hovered = hovered;
+        }
+
+        if let Some(start) = hovered {
+            for button in just_pressed {
+                self.presses.insert(
+                    button,
+                    Press {
+                        start,
+                        last: start.0,
+                        dragging: false,
+                    },
+                );
+            }
+        }
+
+        if let Some((cube, entity)) = hovered {
+            for (button, press) in self.presses.iter_mut() {
+                if cube == press.last {
+                    continue;
+                }
+
+                if !press.dragging {
+                    press.dragging = true;
+                    events.push(PointerEvent::DragStart(HexDragStart {
+                        cube: press.start.0,
+                        button: *button,
+                        entity: press.start.1,
+                    }));
+                }
+                events.push(PointerEvent::Drag(HexDrag {
+                    start: press.start.0,
+                    cube,
+                    button: *button,
+                    entity,
+                }));
+                press.last = cube;
+            }
+        }
+
+        for button in just_released {
+            let press = match self.presses.remove(&button) {
+                Some(press) => press,
+                None => continue,
+            };
+
+            if press.dragging {
+                events.push(PointerEvent::DragEnd(HexDragEnd {
+                    start: press.start.0,
+                    end: hovered.map(|(cube, _)| cube),
+                    button,
+                }));
+            } else if hovered.map(|(cube, _)| cube) == Some(press.start.0) {
+                events.push(PointerEvent::Clicked(HexClicked {
+                    cube: press.start.0,
+                    button,
+                    entity: press.start.1,
+                }));
+            }
+        }
+
+        events
+    }
+}
+
+/// Turns the tile under the cursor, found by `update_hovered_hex`, and the mouse buttons into
+/// hex events.
+#[allow(clippy::too_many_arguments)]
+pub(super) fn emit_hex_events(
+    hovered: Res<HoveredHex>,
+    buttons: Res<Input<MouseButton>>,
+    mut state: Local<PointerState>,
+    mut clicked: EventWriter<HexClicked>,
+    mut hover_enter: EventWriter<HexHoverEnter>,
+    mut hover_exit: EventWriter<HexHoverExit>,
+    mut drag_start: EventWriter<HexDragStart>,
+    mut drag: EventWriter<HexDrag>,
+    mut drag_end: EventWriter<HexDragEnd>,
+) {
+    let events = state.update(
+        hovered.0,
+        buttons.get_just_pressed().copied(),
+        buttons.get_just_released().copied(),
+    );
+
+    for event in events {
+        match event {
+            PointerEvent::Clicked(event) => clicked.send(event),
+            PointerEvent::HoverEnter(event) => hover_enter.send(event),
+            PointerEvent::HoverExit(event) => hover_exit.send(event),
+            PointerEvent::DragStart(event) => drag_start.send(event),
+            PointerEvent::Drag(event) => drag.send(event),
+            PointerEvent::DragEnd(event) => drag_end.send(event),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEFT: MouseButton = MouseButton::Left;
+
+    fn tile(q: i32) -> Option<(Cube, Entity)> {
+        Some((Cube::new(q, 0, -q).unwrap(), Entity::from_raw(q as u32)))
+    }
+
+    #[test]
+    fn hovering_enters_and_exits() {
+        let mut state = PointerState::default();
+
+        let events = state.update(tile(1), [].into_iter(), [].into_iter());
+        assert!(matches!(events[..], [PointerEvent::HoverEnter(_)]));
+
+        assert!(state
+            .update(tile(1), [].into_iter(), [].into_iter())
+            .is_empty());
+
+        let events = state.update(tile(2), [].into_iter(), [].into_iter());
+        assert!(matches!(
+            events[..],
+            [PointerEvent::HoverExit(HexHoverExit { cube: exit, .. }), PointerEvent::HoverEnter(HexHoverEnter { cube: enter, .. })]
+                if exit == tile(1).unwrap().0 && enter == tile(2).unwrap().0
+        ));
+
+        let events = state.update(None, [].into_iter(), [].into_iter());
+        assert!(matches!(events[..], [PointerEvent::HoverExit(_)]));
+    }
+
+    #[test]
+    fn press_and_release_on_same_tile_clicks() {
+        let mut state = PointerState::default();
+        state.update(tile(1), [].into_iter(), [].into_iter());
+
+        assert!(state
+            .update(tile(1), [LEFT].into_iter(), [].into_iter())
+            .is_empty());
+        let events = state.update(tile(1), [].into_iter(), [LEFT].into_iter());
+
+        assert_eq!(
+            events,
+            vec![PointerEvent::Clicked(HexClicked {
+                cube: tile(1).unwrap().0,
+                button: LEFT,
+                entity: tile(1).unwrap().1,
+            })]
+        );
+    }
+
+    #[test]
+    fn moving_while_pressed_drags() {
+        let mut state = PointerState::default();
+        state.update(tile(1), [LEFT].into_iter(), [].into_iter());
+
+        let events = state.update(tile(2), [].into_iter(), [].into_iter());
+        assert!(matches!(
+            events[..],
+            [
+                PointerEvent::HoverExit(_),
+                PointerEvent::HoverEnter(_),
+                PointerEvent::DragStart(_),
+                PointerEvent::Drag(_)
+            ]
+        ));
+
+        let events = state.update(tile(3), [].into_iter(), [].into_iter());
+        assert!(matches!(
+            events[..],
+            [
+                PointerEvent::HoverExit(_),
+                PointerEvent::HoverEnter(_),
+                PointerEvent::Drag(_)
+            ]
+        ));
+
+        // Coming back to the start tile still ends a drag instead of clicking.
+        state.update(tile(1), [].into_iter(), [].into_iter());
+        let events = state.update(tile(1), [].into_iter(), [LEFT].into_iter());
+        assert_eq!(
+            events,
+            vec![PointerEvent::DragEnd(HexDragEnd {
+                start: tile(1).unwrap().0,
+                end: Some(tile(1).unwrap().0),
+                button: LEFT,
+            })]
+        );
+    }
+
+    #[test]
+    fn releasing_off_the_tile_does_not_click() {
+        let mut state = PointerState::default();
+        state.update(tile(1), [LEFT].into_iter(), [].into_iter());
+
+        let events = state.update(None, [].into_iter(), [LEFT].into_iter());
+
+        assert!(matches!(events[..], [PointerEvent::HoverExit(_)]));
+    }
+}
@@ -0,0 +1,329 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+use image::error::{
+    DecodingError, EncodingError, ImageFormatHint, ParameterError, ParameterErrorKind,
+};
+use image::{GrayAlphaImage, ImageError, ImageFormat, ImageResult, LumaA, RgbaImage};
+
+use crate::coordinates::{Axial, Cube, Offset};
+
+use super::map::{HexMap, TileData};
+
+/// Keyword of the PNG text chunk holding the offset coordinate of the top left pixel.
+const ORIGIN_KEYWORD: &str = "hex-origin";
+
+/// How pixels of a heightmap image turn into tiles. Every pixel is the hex at the offset
+/// coordinate of its column and row.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HeightmapSettings {
+    /// Elevation of black pixels.
+    pub min_elevation: f32,
+    /// Elevation of white pixels.
+    pub max_elevation: f32,
+    /// Pixels with a lower luminance get no tile, e.g. to leave out the sea.
+    pub cutoff: Option<f32>,
+    /// Tile kinds by pixel colour. Each pixel gets the kind of the closest colour, or kind 0 if
+    /// there are none.
+    pub kinds: Vec<([u8; 3], u32)>,
+}
+
+impl Default for HeightmapSettings {
+    fn default() -> Self {
+        Self {
+            min_elevation: 0.0,
+            max_elevation: 2.0,
+            cutoff: None,
+            kinds: Vec::new(),
+        }
+    }
+}
+
+impl HeightmapSettings {
+    fn elevation(&self, luminance: f32) -> f32 {
+        self.min_elevation + luminance * (self.max_elevation - self.min_elevation)
+    }
+
+    fn luminance(&self, elevation: f32) -> f32 {
+        let range = self.max_elevation - self.min_elevation;
+        if range.abs() <= f32::EPSILON {
+            return 0.0;
+        }
+        ((elevation - self.min_elevation) / range).clamp(0.0, 1.0)
+    }
+
+    fn kind(&self, [r, g, b]: [u8; 3]) -> u32 {
+        let distance = |[cr, cg, cb]: [u8; 3]| {
+            [(r, cr), (g, cg), (b, cb)]
+                .iter()
+                .map(|(pixel, color)| (*pixel as i32 - *color as i32).pow(2))
+                .sum::<i32>()
+        };
+
+        self.kinds
+            .iter()
+            .min_by_key(|(color, _)| distance(*color))
+            .map_or(0, |(_, kind)| *kind)
+    }
+}
+
+/// Relative luminance of an sRGB colour, from 0 to 1.
+fn luminance([r, g, b]: [u8; 3]) -> f32 {
+    (0.2126 * r as f32 + 0.7152 * g as f32 + 0.0722 * b as f32) / 255.0
+}
+
+/// Tiles for all opaque pixels of `image`, with the top left pixel at `origin`. Pixels that are
+/// more than half transparent are left out, like the hexes without a tile in an exported
+/// heightmap.
+///
+/// Odd rows are shifted, so `origin` needs to be in an even row to keep the layout of the image.
+pub fn tiles_from_image(
+    image: &RgbaImage,
+    origin: Offset,
+    settings: &HeightmapSettings,
+) -> Vec<(Cube, TileData)> {
+    image
+        .enumerate_pixels()
+        .filter(|(_, _, pixel)| pixel.0[3] >= 128)
+        .filter_map(|(x, y, pixel)| {
+            let [r, g, b, _] = pixel.0;
+            let luminance = luminance([r, g, b]);
+
+            if settings.cutoff.is_some_and(|cutoff| luminance < cutoff) {
+                return None;
+            }
+
+            let tile = TileData {
+                kind: settings.kind([r, g, b]),
+                elevation: settings.elevation(luminance),
+                ..TileData::default()
+            };
+            let offset = Offset::new(origin.col + x as i32, origin.row + y as i32);
+            let cube = Cube::from(Axial::from(offset));
+
+            Some((cube, tile))
+        })
+        .collect()
+}
+
+/// Grayscale image of the tile elevations, covering the offset coordinate bounds of the map.
+/// Hexes without a tile are transparent. Returns the offset of the top left pixel as well, which
+/// is always in an even row.
+pub fn image_from_map(map: &HexMap, settings: &HeightmapSettings) -> (GrayAlphaImage, Offset) {
+    let offsets: Vec<_> = map
+        .iter()
+        .map(|(cube, tile)| (Offset::from(cube), tile.elevation))
+        .collect();
+
+    let min_row = offsets.iter().map(|(o, _)| o.row).min().unwrap_or(0);
+    let min = Offset::new(
+        offsets.iter().map(|(o, _)| o.col).min().unwrap_or(0),
+        // Odd rows are shifted, so the image starts at an even row to keep the layout of the
+        // hexes when it is imported again.
+        min_row - min_row.rem_euclid(2),
+    );
+    let max = Offset::new(
+        offsets.iter().map(|(o, _)| o.col).max().unwrap_or(-1),
+        offsets.iter().map(|(o, _)| o.row).max().unwrap_or(-1),
+    );
+
+    let mut image = GrayAlphaImage::new(
+        (max.col - min.col + 1) as u32,
+        (max.row - min.row + 1) as u32,
+    );
+    for (offset, elevation) in offsets {
+        let value = (settings.luminance(elevation) * 255.0).round() as u8;
+        image.put_pixel(
+            (offset.col - min.col) as u32,
+            (offset.row - min.row) as u32,
+            LumaA([value, 255]),
+        );
+    }
+
+    (image, min)
+}
+
+/// Loads a heightmap image. PNG files saved by `save_heightmap` are placed where the map was,
+/// all other images start at offset 0, 0.
+pub fn load_heightmap(
+    path: impl AsRef<Path>,
+    settings: &HeightmapSettings,
+) -> ImageResult<Vec<(Cube, TileData)>> {
+    let path = path.as_ref();
+    let origin = match ImageFormat::from_path(path) {
+        Ok(ImageFormat::Png) => read_png_origin(path)?,
+        _ => None,
+    };
+
+    Ok(tiles_from_image(
+        &image::open(path)?.into_rgba8(),
+        origin.unwrap_or_default(),
+        settings,
+    ))
+}
+
+/// Saves the heightmap of `map`, in the format of the file extension. Only PNG files keep the
+/// position of the map, other formats are imported at offset 0, 0. An empty map has no image,
+/// saving it fails without creating the file.
+pub fn save_heightmap(
+    map: &HexMap,
+    path: impl AsRef<Path>,
+    settings: &HeightmapSettings,
+) -> ImageResult<()> {
+    if map.is_empty() {
+        return Err(ImageError::Parameter(ParameterError::from_kind(
+            ParameterErrorKind::Generic("cannot save the heightmap of an empty map".into()),
+        )));
+    }
+
+    let path = path.as_ref();
+    let (image, origin) = image_from_map(map, settings);
+
+    match ImageFormat::from_path(path)? {
+        ImageFormat::Png => write_png(&image, origin, path),
+        _ => image.save(path),
+    }
+}
+
+fn write_png(image: &GrayAlphaImage, origin: Offset, path: &Path) -> ImageResult<()> {
+    let encoding_error = |error: png::EncodingError| {
+        ImageError::Encoding(EncodingError::new(
+            ImageFormatHint::Exact(ImageFormat::Png),
+            error,
+        ))
+    };
+
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(file, image.width(), image.height());
+    encoder.set_color(png::ColorType::GrayscaleAlpha);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .add_text_chunk(
+            ORIGIN_KEYWORD.into(),
+            format!("{} {}", origin.col, origin.row),
+        )
+        .map_err(encoding_error)?;
+
+    let mut writer = encoder.write_header().map_err(encoding_error)?;
+    writer
+        .write_image_data(image.as_raw())
+        .map_err(encoding_error)?;
+    writer.finish().map_err(encoding_error)
+}
+
+/// Offset of the top left pixel stored by `write_png`, if there is one.
+fn read_png_origin(path: &Path) -> ImageResult<Option<Offset>> {
+    let decoding_error = |error: png::DecodingError| {
+        ImageError::Decoding(DecodingError::new(
+            ImageFormatHint::Exact(ImageFormat::Png),
+            error,
+        ))
+    };
+
+    let decoder = png::Decoder::new(BufReader::new(File::open(path)?));
+    let reader = decoder.read_info().map_err(decoding_error)?;
+
+    let origin = reader
+        .info()
+        .uncompressed_latin1_text
+        .iter()
+        .find(|chunk| chunk.keyword == ORIGIN_KEYWORD)
+        .and_then(|chunk| {
+            let (col, row) = chunk.text.split_once(' ')?;
+            Some(Offset::new(col.parse().ok()?, row.parse().ok()?))
+        });
+
+    Ok(origin)
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+
+    use super::*;
+
+    fn settings() -> HeightmapSettings {
+        HeightmapSettings {
+            min_elevation: -1.0,
+            max_elevation: 1.0,
+            ..HeightmapSettings::default()
+        }
+    }
+
+    #[test]
+    fn pixels_become_tiles_at_their_offset() {
+        let mut image = RgbaImage::new(3, 2);
+        image.put_pixel(0, 0, Rgba([255, 255, 255, 255]));
+        image.put_pixel(2, 1, Rgba([0, 0, 0, 255]));
+        image.put_pixel(1, 1, Rgba([255, 255, 255, 0]));
+
+        let tiles = tiles_from_image(&image, Offset::default(), &settings());
+        let elevation = |col, row| {
+            let cube = Cube::from(Axial::from(Offset::new(col, row)));
+            tiles
+                .iter()
+                .find(|(c, _)| *c == cube)
+                .map(|(_, tile)| tile.elevation)
+        };
+
+        assert_eq!(tiles.len(), 2);
+        assert!((elevation(0, 0).unwrap() - 1.0).abs() < 1e-5);
+        assert!((elevation(2, 1).unwrap() + 1.0).abs() < 1e-5);
+        assert_eq!(elevation(1, 1), None);
+    }
+
+    #[test]
+    fn colours_pick_closest_kind_and_cutoff_removes_dark_pixels() {
+        let mut image = RgbaImage::new(2, 1);
+        image.put_pixel(0, 0, Rgba([40, 180, 30, 255]));
+        image.put_pixel(1, 0, Rgba([10, 10, 60, 255]));
+
+        let settings = HeightmapSettings {
+            cutoff: Some(0.1),
+            kinds: vec![([0, 0, 255], 0), ([0, 200, 0], 1)],
+            ..settings()
+        };
+        let tiles = tiles_from_image(&image, Offset::default(), &settings);
+
+        assert_eq!(tiles.len(), 1);
+        assert_eq!(tiles[0].1.kind, 1);
+    }
+
+    #[test]
+    fn exported_png_round_trips() {
+        let mut map = HexMap::default();
+        let centre = Cube::from(Axial::from(Offset::new(-3, -5)));
+        for (i, cube) in centre.range(2).enumerate() {
+            map.insert(
+                cube,
+                TileData {
+                    elevation: (i as f32 / 9.0) - 1.0,
+                    ..TileData::default()
+                },
+            );
+        }
+
+        let path = std::env::temp_dir().join(format!("heightmap-{}.png", std::process::id()));
+        save_heightmap(&map, &path, &settings()).unwrap();
+        let tiles = load_heightmap(&path, &settings());
+        std::fs::remove_file(&path).unwrap();
+        let tiles = tiles.unwrap();
+
+        assert_eq!(tiles.len(), map.len());
+        for (cube, tile) in tiles {
+            let expected = map.get(cube).unwrap().elevation;
+            assert!((tile.elevation - expected).abs() < 2.0 / 255.0);
+        }
+    }
+
+    #[test]
+    fn empty_map_is_not_saved() {
+        let path = std::env::temp_dir().join(format!("empty-heightmap-{}.png", std::process::id()));
+
+        let result = save_heightmap(&HexMap::default(), &path, &settings());
+
+        assert!(matches!(result, Err(ImageError::Parameter(_))));
+        assert!(!path.exists());
+    }
+}
@@ -1,133 +1,316 @@
 use std::collections::HashMap;
 
 use bevy::prelude::*;
-use bevy_rapier3d::prelude::*;
 
 use crate::{
-    coordinates::Axial, coordinates::Cube, coordinates::Offset,
-    input::camera_control_plugin::CurrentCameraTag, mesh_generation::hex::create_hex_prism,
+    coordinates::Axial,
+    coordinates::Cube,
+    input::cursor_ray::{update_cursor_ray, CursorRay},
 };
 
-use itertools::Itertools;
+use super::editor::{edit_map, editor_keys, MapEditor};
+use super::events::{
+    emit_hex_events, HexClicked, HexDrag, HexDragEnd, HexDragStart, HexHoverEnter, HexHoverExit,
+    HoveredHex,
+};
+use super::heightmap::load_heightmap;
+use super::history::EditHistory;
+use super::hover::{highlight_hovered, spawn_hover_markers, update_tooltip, HexTooltipSettings};
+use super::index::{update_entity_index, HexEntityIndex};
+use super::lod::{rebuild_chunks, update_lod, HexChunks};
+use super::map::{HexMap, TileData};
+use super::map_file::MapMetadata;
+use super::picking::pick_hex;
+use super::save::{
+    export_heightmaps, import_heightmaps, import_tiled_maps, load_maps, save_maps, ExportHeightmap,
+    ImportHeightmap, ImportTiled, LoadMap, SaveMap,
+};
+use super::selection::{
+    emit_selection_changes, highlight_selection, select_hexes, HexSelection, HexSelectionChanged,
+    SelectionTool,
+};
+use super::settings::HexWorldSettings;
+use super::tiled::load_tiled;
+use super::tiles::{sync_tiles, tile_top, TileMaterials};
 
 use bevy_trafo::Trafo;
 
-#[derive(Component)]
-struct Hexagon(Cube);
+/// Marks an entity as the tile at a hex coordinate.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Hexagon(Cube);
+
+impl Hexagon {
+    pub fn new(cube: Cube) -> Self {
+        Self(cube)
+    }
+
+    pub fn cube(&self) -> Cube {
+        self.0
+    }
+}
 
-#[derive(Component)]
-struct Energy {
+/// Vertical motion of a tile, driven by the wave simulation.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct Energy {
     velocity: Vec3,
     // acceleration: Vec3,
 }
 
-pub struct HexWorld;
+impl Energy {
+    pub fn velocity(&self) -> Vec3 {
+        self.velocity
+    }
 
-impl Plugin for HexWorld {
-    fn build(&self, app: &mut App) {
-        app.add_startup_system(setup)
-            .add_system(select_hex)
-            .add_system(integrate)
-            .add_system(distribute_velocity.after(integrate));
+    /// Pushes the tile, which then spreads to its neighbours as a wave.
+    pub fn add_velocity(&mut self, velocity: Vec3) {
+        self.velocity += velocity;
     }
 }
 
-fn get_coordinates() -> impl Iterator<Item = Axial> {
-    (-25..=25)
-        .cartesian_product(-25..=25)
-        .map(|(x, y)| Offset::new(x, y).into())
+/// A tile that `HexWorld` picks, indexes and animates. Use `HexTileBundle::new` to place it at
+/// the world position of its coordinate.
+#[derive(Bundle)]
+pub struct HexTileBundle {
+    pub hexagon: Hexagon,
+    pub energy: Energy,
+    #[bundle]
+    pub pbr: PbrBundle,
+}
+
+impl HexTileBundle {
+    pub fn new(cube: Cube, mesh: Handle<Mesh>, material: Handle<StandardMaterial>) -> Self {
+        Self {
+            hexagon: Hexagon::new(cube),
+            energy: Energy::default(),
+            pbr: PbrBundle {
+                mesh,
+                material,
+                transform: Transform::from_translation(Axial::from(cube).into()),
+                ..default()
+            },
+        }
+    }
+}
 
-    // Cube::origin()
-    //     .neighbours()
-    //     .map(|c| c.into())
-    //     .chain(std::iter::once(Axial::origin()))
+/// Labels of the `HexWorld` systems, to order other systems relative to them.
+#[derive(SystemLabel, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum HexWorldSystem {
+    /// Startup system filling `HexMap` and creating the tile materials.
+    Setup,
+    /// Spawns, updates and despawns tiles to match `HexMap`. Edits made before it show up in
+    /// the same frame.
+    SyncTiles,
+    /// Finds the tile under the cursor and sends the hex input events.
+    SelectHex,
+    /// Moves the tiles according to their `Energy`.
+    Integrate,
+    /// Spreads `Energy` from tiles to their neighbours.
+    DistributeVelocity,
+}
+
+pub struct HexWorld;
+
+impl Plugin for HexWorld {
+    fn build(&self, app: &mut App) {
+        if app.world.get_resource::<HexWorldSettings>().is_none() {
+            app.insert_resource(HexWorldSettings::default());
+        }
+        if app.world.get_resource::<HexTooltipSettings>().is_none() {
+            app.insert_resource(HexTooltipSettings::default());
+        }
+
+        app.add_event::<HexClicked>()
+            .add_event::<HexHoverEnter>()
+            .add_event::<HexHoverExit>()
+            .add_event::<HexDragStart>()
+            .add_event::<HexDrag>()
+            .add_event::<HexDragEnd>()
+            .add_event::<HexSelectionChanged>()
+            .add_event::<SaveMap>()
+            .add_event::<LoadMap>()
+            .add_event::<ImportHeightmap>()
+            .add_event::<ExportHeightmap>()
+            .add_event::<ImportTiled>()
+            .init_resource::<HoveredHex>()
+            .init_resource::<HexEntityIndex>()
+            .init_resource::<HexSelection>()
+            .init_resource::<SelectionTool>()
+            .init_resource::<HexMap>()
+            .init_resource::<HexChunks>()
+            .init_resource::<MapEditor>()
+            .init_resource::<EditHistory>()
+            .init_resource::<MapMetadata>()
+            .add_startup_system(setup.label(HexWorldSystem::Setup))
+            .add_startup_system(spawn_hover_markers)
+            .add_system(
+                update_hovered_hex
+                    .label(HexWorldSystem::SelectHex)
+                    .after(update_cursor_ray),
+            )
+            .add_system(
+                emit_hex_events
+                    .label(HexWorldSystem::SelectHex)
+                    .after(update_hovered_hex),
+            )
+            .add_system(highlight_hovered.after(update_hovered_hex))
+            .add_system(update_tooltip.after(update_hovered_hex))
+            .add_system(start_wave.after(emit_hex_events))
+            .add_system(select_hexes.after(emit_hex_events))
+            .add_system(emit_selection_changes.after(select_hexes))
+            .add_system(highlight_selection.after(emit_selection_changes))
+            .add_system(editor_keys)
+            .add_system(
+                edit_map
+                    .after(editor_keys)
+                    .after(HexWorldSystem::SelectHex)
+                    .before(HexWorldSystem::SyncTiles),
+            )
+            .add_system(save_maps.after(editor_keys))
+            .add_system(export_heightmaps.after(editor_keys))
+            .add_system(
+                import_heightmaps
+                    .after(editor_keys)
+                    .after(edit_map)
+                    .before(HexWorldSystem::SyncTiles),
+            )
+            .add_system(
+                import_tiled_maps
+                    .after(editor_keys)
+                    .after(edit_map)
+                    .before(HexWorldSystem::SyncTiles),
+            )
+            .add_system(
+                load_maps
+                    .after(editor_keys)
+                    .after(edit_map)
+                    .before(HexWorldSystem::SyncTiles),
+            )
+            .add_system(
+                sync_tiles
+                    .label(HexWorldSystem::SyncTiles)
+                    .after(highlight_selection),
+            )
+            .add_system(rebuild_chunks.after(HexWorldSystem::SyncTiles))
+            .add_system(update_lod.after(rebuild_chunks))
+            .add_system_to_stage(CoreStage::PostUpdate, update_entity_index)
+            .add_system(integrate.label(HexWorldSystem::Integrate))
+            .add_system(
+                distribute_velocity
+                    .label(HexWorldSystem::DistributeVelocity)
+                    .after(HexWorldSystem::Integrate),
+            );
+    }
 }
 
 fn setup(
     mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
+    mut map: ResMut<HexMap>,
+    settings: Res<HexWorldSettings>,
 ) {
-    let material = materials.add(StandardMaterial {
-        base_color: Color::rgb_u8(0, 98, 105),
-        metallic: 0.8,
-        ..default()
+    let mut add_material = |color: Color| {
+        materials.add(StandardMaterial {
+            base_color: color,
+            metallic: settings.metallic,
+            ..default()
+        })
+    };
+
+    commands.insert_resource(TileMaterials {
+        kinds: settings
+            .tile_colors
+            .iter()
+            .copied()
+            .map(&mut add_material)
+            .collect(),
+        selected: add_material(settings.selected_color),
     });
 
-    let mesh = meshes.add(create_hex_prism(0.49, 0.25).into());
-
-    for axial in get_coordinates() {
-        commands
-            .spawn_bundle(PbrBundle {
-                mesh: mesh.clone(),
-                material: material.clone(),
-                transform: Transform {
-                    translation: axial.into(),
-                    ..default()
-                },
-                ..default()
-            })
-            .insert(Hexagon(axial.into()))
-            .insert(RigidBody::KinematicPositionBased)
-            .insert(
-                Collider::from_bevy_mesh(
-                    Assets::get(&meshes, &mesh).unwrap(),
-                    &ComputedColliderShape::TriMesh,
-                )
-                .unwrap(),
-            )
-            .insert(Energy {
-                velocity: Vec3::ZERO,
-            });
+    // A map filled before the plugin was added is kept.
+    if !map.is_empty() {
+        return;
+    }
+
+    let heightmap = settings.heightmap.as_ref().and_then(|path| {
+        load_heightmap(path, &settings.heightmap_settings)
+            .map_err(|error| error!("Could not load heightmap {}: {}", path.display(), error))
+            .ok()
+    });
+    let tiles = heightmap.or_else(|| {
+        let path = settings.tiled.as_ref()?;
+        load_tiled(path, &settings.tiled_settings)
+            .map_err(|error| error!("Could not load Tiled map {}: {}", path.display(), error))
+            .ok()
+    });
+
+    match tiles {
+        Some(tiles) => map.replace(tiles),
+        None => map.replace(
+            settings
+                .shape
+                .cubes()
+                .into_iter()
+                .map(|cube| (cube, TileData::default())),
+        ),
     }
 }
 
-fn select_hex(
-    rapier_context: Res<RapierContext>,
-    camera_query: Query<(&GlobalTransform, &Camera), With<CurrentCameraTag>>,
-    mut hexagon_query: Query<(Entity, &Hexagon, &mut Energy)>,
-    btn: Res<Input<MouseButton>>,
+fn update_hovered_hex(
+    cursor_ray: Res<CursorRay>,
+    index: Res<HexEntityIndex>,
+    map: Res<HexMap>,
+    settings: Res<HexWorldSettings>,
+    hexagon_query: Query<&Transform, With<Hexagon>>,
+    mut hovered: ResMut<HoveredHex>,
 ) {
-    if !btn.just_pressed(MouseButton::Left) {
+    let top = |cube: Cube| {
+        let transform = hexagon_query.get(index.get(cube)?).ok()?;
+        Some(transform.translation.y + tile_top(&settings, map.get(cube)?))
+    };
+
+    let max_toi = 100.;
+    let hit = cursor_ray.0.and_then(|ray| {
+        pick_hex(ray.origin, ray.direction, max_toi, top)
+            .and_then(|hit| Some((hit.cube, index.get(hit.cube)?)))
+    });
+
+    if hovered.0 != hit {
+        hovered.0 = hit;
+    }
+}
+
+fn start_wave(
+    settings: Res<HexWorldSettings>,
+    editor: Res<MapEditor>,
+    mut clicked: EventReader<HexClicked>,
+    mut energy_query: Query<&mut Energy>,
+) {
+    // Clicks edit the map while the editor is open.
+    if editor.enabled {
         return;
     }
-    let (transform, camera) = camera_query.single();
-
-    let ray_origin = camera
-        .ndc_to_world(
-            transform,
-            Vec3 {
-                x: 0.,
-                y: 0.,
-                z: 1.,
-            },
-        )
-        .unwrap();
-    let ray_dir = transform.forward();
 
-    let max_toi = 100.;
-    let solid = true;
-    if let Some((entity, _toi)) =
-        rapier_context.cast_ray(ray_origin, ray_dir, max_toi, solid, Default::default())
-    {
-        if let Some((entity, hex, mut energy)) = hexagon_query
-            .iter_mut()
-            .find(|(ent, _hex, _energy)| ent.id() == entity.id())
-        {
-            let coord = Offset::from(hex.0);
-            info!("{}", coord);
-
-            energy.velocity += Vec3::Y * 30.;
+    for event in clicked.iter() {
+        if event.button != MouseButton::Left {
+            continue;
+        }
+
+        if let Ok(mut energy) = energy_query.get_mut(event.entity) {
+            energy.add_velocity(Vec3::Y * settings.wave.click_impulse);
         }
     }
 }
 
-fn integrate(time: Res<Time>, mut query: Query<(&mut Transform, &mut Energy)>) {
+fn integrate(
+    time: Res<Time>,
+    settings: Res<HexWorldSettings>,
+    mut query: Query<(&mut Transform, &mut Energy)>,
+) {
     for (mut t, mut e) in query.iter_mut() {
-        let spring = 10.;
+        let spring = settings.wave.spring;
         let mass = 1.0;
         let x = t.translation.y;
-        let damp = 1.5;
+        let damp = settings.wave.damping;
         let acceleration = -(spring / mass) * x - damp * e.velocity.y;
 
         let acceleration = acceleration * Vec3::Y;
@@ -141,7 +324,11 @@ fn integrate(time: Res<Time>, mut query: Query<(&mut Transform, &mut Energy)>) {
     }
 }
 
-fn distribute_velocity(time: Res<Time>, mut query: Query<(&Hexagon, &Transform, &mut Energy)>) {
+fn distribute_velocity(
+    time: Res<Time>,
+    settings: Res<HexWorldSettings>,
+    mut query: Query<(&Hexagon, &Transform, &mut Energy)>,
+) {
     let height_map: HashMap<_, _> = query
         .iter()
         .map(|(h, t, _)| (h.0, t.translation.y))
@@ -156,14 +343,14 @@ fn distribute_velocity(time: Res<Time>, mut query: Query<(&Hexagon, &Transform,
         //     continue;
         // }
         for neighbour in hex.0.neighbours() {
-            let spring = 1.;
+            let spring = settings.wave.neighbour_spring;
             let mass = 1.0;
             let x = (t.translation.y - height_map.get(&neighbour).unwrap_or(&0.0)).abs();
-            let damp = 0.1;
+            let damp = settings.wave.neighbour_damping;
             let acceleration = -(spring / mass) * x - damp * e.velocity.y;
 
             // info!("{} {} {}", hex.0, neighbour, acceleration);
-            let acceleration = 0.9 * acceleration * Vec3::Y;
+            let acceleration = settings.wave.transfer * acceleration * Vec3::Y;
             *dist.entry(neighbour).or_insert(Vec3::ZERO) -= acceleration;
             *dist.entry(hex.0).or_insert(Vec3::ZERO) += acceleration;
         }
@@ -0,0 +1,255 @@
+use std::collections::VecDeque;
+
+use crate::coordinates::Cube;
+
+use super::map::{EditAction, HexMap, TileData};
+
+/// Number of transactions `EditHistory::default` keeps.
+const DEFAULT_CAPACITY: usize = 100;
+
+/// Reversible change of a single tile.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TileEdit {
+    pub cube: Cube,
+    pub before: Option<TileData>,
+    pub after: Option<TileData>,
+}
+
+impl TileEdit {
+    fn undo(&self, map: &mut HexMap) {
+        map.set(self.cube, self.before.clone());
+    }
+
+    fn redo(&self, map: &mut HexMap) {
+        map.set(self.cube, self.after.clone());
+    }
+}
+
+/// Edits that are undone and redone together, e.g. everything painted in one brush stroke.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Transaction {
+    edits: Vec<TileEdit>,
+}
+
+impl Transaction {
+    pub fn edits(&self) -> &[TileEdit] {
+        &self.edits
+    }
+
+    fn undo(&self, map: &mut HexMap) {
+        // In reverse, so a hex edited twice ends up with the tile from before the first edit.
+        for edit in self.edits.iter().rev() {
+            edit.undo(map);
+        }
+    }
+
+    fn redo(&self, map: &mut HexMap) {
+        for edit in &self.edits {
+            edit.redo(map);
+        }
+    }
+}
+
+/// Undo and redo history of `HexMap` edits, keeping at most `capacity` transactions.
+///
+/// Edits made through `apply` between `begin` and `commit` form one transaction, edits outside
+/// of one are a transaction on their own.
+#[derive(Clone, Debug)]
+pub struct EditHistory {
+    undo: VecDeque<Transaction>,
+    redo: Vec<Transaction>,
+    current: Option<Transaction>,
+    capacity: usize,
+}
+
+impl Default for EditHistory {
+    fn default() -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY)
+    }
+}
+
+impl EditHistory {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            current: None,
+            capacity,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty() || self.current.as_ref().is_some_and(|t| !t.edits.is_empty())
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Starts a transaction, committing the one still open.
+    pub fn begin(&mut self) {
+        self.commit();
+        self.current = Some(Transaction::default());
+    }
+
+    /// Ends the open transaction. Transactions without edits are dropped.
+    pub fn commit(&mut self) {
+        if let Some(transaction) = self.current.take() {
+            self.push(transaction);
+        }
+    }
+
+    /// Applies `action` to the tile at `cube` and records the change.
+    pub fn apply(&mut self, map: &mut HexMap, cube: Cube, action: &EditAction) {
+        let before = map.apply(cube, action);
+        let after = map.get(cube).cloned();
+
+        if before != after {
+            self.record(TileEdit {
+                cube,
+                before,
+                after,
+            });
+        }
+    }
+
+    /// Records an edit that was already made to the map.
+    pub fn record(&mut self, edit: TileEdit) {
+        match self.current.as_mut() {
+            Some(transaction) => transaction.edits.push(edit),
+            None => self.push(Transaction { edits: vec![edit] }),
+        }
+    }
+
+    /// Reverts the last transaction. Returns whether there was one.
+    pub fn undo(&mut self, map: &mut HexMap) -> bool {
+        self.commit();
+
+        match self.undo.pop_back() {
+            Some(transaction) => {
+                transaction.undo(map);
+                self.redo.push(transaction);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Makes the last undone transaction again. Returns whether there was one.
+    pub fn redo(&mut self, map: &mut HexMap) -> bool {
+        self.commit();
+
+        match self.redo.pop() {
+            Some(transaction) => {
+                transaction.redo(map);
+                self.undo.push_back(transaction);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.current = None;
+    }
+
+    fn push(&mut self, transaction: Transaction) {
+        if transaction.edits.is_empty() {
+            return;
+        }
+
+        // A new edit makes the undone ones unreachable.
+        self.redo.clear();
+        self.undo.push_back(transaction);
+        while self.undo.len() > self.capacity {
+            self.undo.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cube(q: i32) -> Cube {
+        Cube::new(q, 0, -q).unwrap()
+    }
+
+    fn elevation(map: &HexMap, q: i32) -> Option<f32> {
+        map.get(cube(q)).map(|tile| tile.elevation)
+    }
+
+    fn flat_map() -> HexMap {
+        let mut map = HexMap::default();
+        map.replace((0..3).map(|q| (cube(q), TileData::default())));
+        map
+    }
+
+    #[test]
+    fn transaction_is_undone_and_redone_as_one() {
+        let mut map = flat_map();
+        let mut history = EditHistory::default();
+
+        history.begin();
+        history.apply(&mut map, cube(0), &EditAction::Raise(1.0));
+        history.apply(&mut map, cube(0), &EditAction::Raise(1.0));
+        history.apply(&mut map, cube(1), &EditAction::Remove);
+        history.commit();
+
+        assert!(history.undo(&mut map));
+        assert_eq!(elevation(&map, 0), Some(0.0));
+        assert!(map.contains(cube(1)));
+        assert!(!history.undo(&mut map));
+
+        assert!(history.redo(&mut map));
+        assert_eq!(elevation(&map, 0), Some(2.0));
+        assert!(!map.contains(cube(1)));
+        assert!(!history.redo(&mut map));
+    }
+
+    #[test]
+    fn new_edit_clears_redo() {
+        let mut map = flat_map();
+        let mut history = EditHistory::default();
+
+        history.apply(&mut map, cube(0), &EditAction::Raise(1.0));
+        history.undo(&mut map);
+        assert!(history.can_redo());
+
+        history.apply(&mut map, cube(1), &EditAction::Raise(1.0));
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn edits_without_change_are_not_recorded() {
+        let mut map = flat_map();
+        let mut history = EditHistory::default();
+
+        history.begin();
+        history.apply(&mut map, cube(0), &EditAction::Add(TileData::default()));
+        history.apply(&mut map, cube(5), &EditAction::Raise(1.0));
+        history.commit();
+
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn history_drops_oldest_transactions() {
+        let mut map = flat_map();
+        let mut history = EditHistory::with_capacity(2);
+
+        for _ in 0..3 {
+            history.apply(&mut map, cube(0), &EditAction::Raise(1.0));
+        }
+
+        while history.undo(&mut map) {}
+
+        assert_eq!(elevation(&map, 0), Some(1.0));
+    }
+}
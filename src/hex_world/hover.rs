@@ -0,0 +1,211 @@
+use bevy::prelude::*;
+
+use crate::{
+    coordinates::{Axial, Cube, Offset},
+    input::cursor_ray::CursorWindow,
+    mesh_generation::outline::create_hex_outline,
+};
+
+use super::events::HoveredHex;
+use super::hex_world::Energy;
+use super::map::{HexMap, TileData};
+use super::settings::HexWorldSettings;
+use super::tiles::tile_top;
+
+/// Settings for the overlay showing information about the tile under the cursor.
+pub struct HexTooltipSettings {
+    pub enabled: bool,
+    /// Font asset used for the overlay, relative to the assets folder. Bevy has no built-in
+    /// font, so there is no overlay without one, the app ships `fonts/DejaVuSansMono.ttf`. Only
+    /// read when the world is set up.
+    pub font: Option<String>,
+    pub font_size: f32,
+}
+
+impl Default for HexTooltipSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            font: None,
+            font_size: 16.0,
+        }
+    }
+}
+
+#[derive(Component)]
+pub(super) struct HoverOutline;
+
+#[derive(Component)]
+pub(super) struct HexTooltip;
+
+fn tooltip_text(cube: Cube, tile: &TileData, wave: f32, velocity: f32) -> String {
+    format!(
+        "{}\n{}\n{}\nKind {}\nElevation {:.2}\nWave {:.2}\nVelocity {:.2}",
+        Offset::from(cube),
+        Axial::from(cube),
+        cube,
+        tile.kind,
+        tile.elevation,
+        wave,
+        velocity
+    )
+}
+
+pub(super) fn spawn_hover_markers(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    asset_server: Res<AssetServer>,
+    settings: Res<HexTooltipSettings>,
+    world_settings: Res<HexWorldSettings>,
+) {
+    commands
+        .spawn_bundle(PbrBundle {
+            mesh: meshes.add(create_hex_outline(world_settings.tile_size, 0.04).into()),
+            material: materials.add(StandardMaterial {
+                base_color: Color::WHITE,
+                unlit: true,
+                ..default()
+            }),
+            visibility: Visibility { is_visible: false },
+            ..default()
+        })
+        .insert(HoverOutline);
+
+    let font = match &settings.font {
+        Some(font) => asset_server.load(font.as_str()),
+        None => {
+            if settings.enabled {
+                warn!("No font set in HexTooltipSettings, the hover overlay is disabled");
+            }
+            return;
+        }
+    };
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                display: Display::None,
+                position_type: PositionType::Absolute,
+                padding: UiRect::all(Val::Px(4.0)),
+                ..default()
+            },
+            color: Color::rgba(0.0, 0.0, 0.0, 0.7).into(),
+            ..default()
+        })
+        .insert(HexTooltip)
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle::from_section(
+                "",
+                TextStyle {
+                    font,
+                    font_size: settings.font_size,
+                    color: Color::WHITE,
+                },
+            ));
+        });
+}
+
+/// Puts the outline on top of the hovered tile. It follows the tile every frame, as tiles move
+/// up and down while a wave passes.
+pub(super) fn highlight_hovered(
+    hovered: Res<HoveredHex>,
+    map: Res<HexMap>,
+    settings: Res<HexWorldSettings>,
+    tile_query: Query<&Transform, Without<HoverOutline>>,
+    mut outline_query: Query<(&mut Transform, &mut Visibility), With<HoverOutline>>,
+) {
+    let tile = hovered.0.and_then(|(cube, entity)| {
+        let top = tile_top(&settings, map.get(cube)?);
+        Some(tile_query.get(entity).ok()?.translation + top * Vec3::Y)
+    });
+
+    for (mut transform, mut visibility) in outline_query.iter_mut() {
+        match tile {
+            Some(top) => {
+                // Lifted a little to keep it from flickering with the tile top.
+                transform.translation = top + 0.005 * Vec3::Y;
+                visibility.is_visible = true;
+            }
+            None if visibility.is_visible => visibility.is_visible = false,
+            None => {}
+        }
+    }
+}
+
+pub(super) fn update_tooltip(
+    windows: Res<Windows>,
+    cursor_window: Res<CursorWindow>,
+    hovered: Res<HoveredHex>,
+    map: Res<HexMap>,
+    settings: Res<HexTooltipSettings>,
+    tile_query: Query<(&Transform, &Energy)>,
+    mut tooltip_query: Query<(&mut Style, &Children), With<HexTooltip>>,
+    mut text_query: Query<&mut Text>,
+) {
+    let tile = hovered
+        .0
+        .filter(|_| settings.enabled)
+        .and_then(|(cube, entity)| Some((cube, map.get(cube)?, tile_query.get(entity).ok()?)));
+
+    for (mut style, children) in tooltip_query.iter_mut() {
+        let (cube, tile, (transform, energy)) = match tile {
+            Some(tile) => tile,
+            None => {
+                if style.display != Display::None {
+                    style.display = Display::None;
+                }
+                continue;
+            }
+        };
+
+        // While the cursor is grabbed the hovered tile is the one in the screen centre.
+        let cursor = cursor_window
+            .0
+            .and_then(|id| windows.get(id))
+            .and_then(|window| {
+                window
+                    .cursor_position()
+                    .or_else(|| Some(Vec2::new(window.width(), window.height()) / 2.0))
+            });
+
+        style.display = Display::Flex;
+        if let Some(cursor) = cursor {
+            style.position = UiRect {
+                left: Val::Px(cursor.x + 16.0),
+                bottom: Val::Px(cursor.y + 16.0),
+                ..default()
+            };
+        }
+
+        for child in children.iter() {
+            if let Ok(mut text) = text_query.get_mut(*child) {
+                text.sections[0].value =
+                    tooltip_text(cube, tile, transform.translation.y, energy.velocity().y);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tooltip_lists_all_coordinates() {
+        let cube = Cube::new(1, 2, -3).unwrap();
+
+        let tile = TileData {
+            kind: 2,
+            elevation: 0.5,
+            ..TileData::default()
+        };
+
+        let text = tooltip_text(cube, &tile, 0.25, -1.0);
+
+        assert_eq!(
+            text,
+            "O[2, 2]\nA[1, 2]\nC[1, 2, -3]\nKind 2\nElevation 0.50\nWave 0.25\nVelocity -1.00"
+        );
+    }
+}
@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::coordinates::Cube;
+
+use super::hex_world::Hexagon;
+
+/// Tile entity at every hex coordinate. The coordinate of an entity is its `Hexagon` component.
+///
+/// Kept in sync with spawned, changed and despawned tiles at the end of every frame.
+#[derive(Default)]
+pub struct HexEntityIndex {
+    entities: HashMap<Cube, Entity>,
+    // Needed to find the coordinate of a tile once its `Hexagon` component is gone.
+    cubes: HashMap<Entity, Cube>,
+}
+
+impl HexEntityIndex {
+    pub fn get(&self, cube: Cube) -> Option<Entity> {
+        self.entities.get(&cube).copied()
+    }
+
+    pub fn contains(&self, cube: Cube) -> bool {
+        self.entities.contains_key(&cube)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Cube, Entity)> + '_ {
+        self.entities.iter().map(|(cube, entity)| (*cube, *entity))
+    }
+
+    fn insert(&mut self, cube: Cube, entity: Entity) {
+        self.remove(entity);
+        if let Some(previous) = self.entities.insert(cube, entity) {
+            self.cubes.remove(&previous);
+        }
+        self.cubes.insert(entity, cube);
+    }
+
+    fn remove(&mut self, entity: Entity) {
+        if let Some(cube) = self.cubes.remove(&entity) {
+            if self.entities.get(&cube) == Some(&entity) {
+                self.entities.remove(&cube);
+            }
+        }
+    }
+}
+
+pub(super) fn update_entity_index(
+    mut index: ResMut<HexEntityIndex>,
+    changed_query: Query<(Entity, &Hexagon), Changed<Hexagon>>,
+    removed: RemovedComponents<Hexagon>,
+) {
+    for entity in removed.iter() {
+        index.remove(entity);
+    }
+
+    for (entity, hexagon) in changed_query.iter() {
+        index.insert(hexagon.cube(), entity);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cube(q: i32) -> Cube {
+        Cube::new(q, 0, -q).unwrap()
+    }
+
+    #[test]
+    fn insert_and_remove_tiles() {
+        let mut index = HexEntityIndex::default();
+        let (a, b) = (Entity::from_raw(1), Entity::from_raw(2));
+
+        index.insert(cube(0), a);
+        index.insert(cube(1), b);
+        assert_eq!(index.get(cube(0)), Some(a));
+        assert_eq!(index.len(), 2);
+
+        index.remove(a);
+        assert_eq!(index.get(cube(0)), None);
+        assert_eq!(index.get(cube(1)), Some(b));
+    }
+
+    #[test]
+    fn moving_a_tile_frees_its_old_hex() {
+        let mut index = HexEntityIndex::default();
+        let tile = Entity::from_raw(1);
+
+        index.insert(cube(0), tile);
+        index.insert(cube(3), tile);
+
+        assert!(!index.contains(cube(0)));
+        assert_eq!(index.get(cube(3)), Some(tile));
+        assert_eq!(index.len(), 1);
+    }
+
+    #[test]
+    fn replaced_tile_keeps_new_entity() {
+        let mut index = HexEntityIndex::default();
+        let (old, new) = (Entity::from_raw(1), Entity::from_raw(2));
+
+        index.insert(cube(0), old);
+        index.insert(cube(0), new);
+        index.remove(old);
+
+        assert_eq!(index.get(cube(0)), Some(new));
+    }
+}
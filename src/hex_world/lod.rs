@@ -0,0 +1,215 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy::prelude::*;
+
+use crate::{
+    coordinates::{Axial, Cube, Offset},
+    input::camera_control_plugin::CurrentCameraTag,
+    mesh_generation::lod::create_elevated_hex_chunk_lod,
+};
+
+use super::map::{HexMap, TileData};
+use super::settings::HexWorldSettings;
+use super::tiles::{tile_top, TileMaterials};
+
+/// Width and height of a chunk in offset coordinates.
+const CHUNK_SIZE: i32 = 8;
+
+/// Camera distances at which chunks switch to the next coarser level of detail.
+const LOD_DISTANCES: [f32; 2] = [30.0, 60.0];
+
+/// Chunk that all tiles within `CHUNK_SIZE` x `CHUNK_SIZE` offset coordinates belong to.
+pub(super) fn chunk_key(cube: Cube) -> IVec2 {
+    let offset = Offset::from(cube);
+    IVec2::new(
+        offset.col.div_euclid(CHUNK_SIZE),
+        offset.row.div_euclid(CHUNK_SIZE),
+    )
+}
+
+/// Level of detail for a chunk at `distance` from the camera, 0 being the full detail tiles.
+fn lod_level(distance: f32, thresholds: &[f32]) -> usize {
+    thresholds
+        .iter()
+        .filter(|threshold| distance >= **threshold)
+        .count()
+}
+
+/// Merged low detail mesh for a chunk, shown instead of its tiles when the camera is far away.
+#[derive(Component)]
+pub(super) struct HexChunk {
+    key: IVec2,
+    centre: Vec3,
+    levels: Vec<Handle<Mesh>>,
+}
+
+/// Marks a tile as belonging to the chunk with the given key.
+#[derive(Component)]
+pub(super) struct ChunkMember(pub(super) IVec2);
+
+/// Chunk entities by key, and the chunks whose tiles changed since their meshes were built.
+#[derive(Default)]
+pub(super) struct HexChunks {
+    entities: HashMap<IVec2, Entity>,
+    dirty: HashSet<IVec2>,
+}
+
+impl HexChunks {
+    pub(super) fn mark_dirty(&mut self, cube: Cube) {
+        self.dirty.insert(chunk_key(cube));
+    }
+}
+
+/// Builds the coarse meshes of chunks whose tiles changed, spawning and despawning chunk
+/// entities as chunks gain their first or lose their last tile.
+pub(super) fn rebuild_chunks(
+    mut commands: Commands,
+    mut chunks: ResMut<HexChunks>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    map: Res<HexMap>,
+    materials: Res<TileMaterials>,
+    settings: Res<HexWorldSettings>,
+) {
+    if chunks.dirty.is_empty() {
+        return;
+    }
+
+    let mut tiles: HashMap<IVec2, Vec<(Cube, &TileData)>> = HashMap::new();
+    for (cube, tile) in map.iter() {
+        let key = chunk_key(cube);
+        if chunks.dirty.contains(&key) {
+            tiles.entry(key).or_default().push((cube, tile));
+        }
+    }
+
+    let dirty: Vec<_> = chunks.dirty.drain().collect();
+    for key in dirty {
+        let tiles = match tiles.get(&key) {
+            Some(tiles) => tiles,
+            None => {
+                if let Some(entity) = chunks.entities.remove(&key) {
+                    commands.entity(entity).despawn();
+                }
+                continue;
+            }
+        };
+
+        let centre = tiles
+            .iter()
+            .map(|(cube, _)| Vec3::from(Axial::from(*cube)))
+            .fold(Vec3::ZERO, |sum, position| sum + position)
+            / tiles.len() as f32;
+
+        let heights: Vec<_> = tiles
+            .iter()
+            .map(|(cube, tile)| (*cube, tile_top(&settings, tile)))
+            .collect();
+        let levels: Vec<_> = (1..=LOD_DISTANCES.len() as u8)
+            .map(|level| {
+                meshes
+                    .add(create_elevated_hex_chunk_lod(&heights, settings.tile_size, level).into())
+            })
+            .collect();
+
+        // The merged mesh has a single material, so it takes the one of the most common kind.
+        let mut kinds: HashMap<u32, usize> = HashMap::new();
+        for (_, tile) in tiles {
+            *kinds.entry(tile.kind).or_default() += 1;
+        }
+        let kind = kinds
+            .into_iter()
+            .max_by_key(|(kind, count)| (*count, std::cmp::Reverse(*kind)))
+            .map_or(0, |(kind, _)| kind);
+
+        let chunk = HexChunk {
+            key,
+            centre,
+            levels,
+        };
+
+        match chunks.entities.get(&key) {
+            Some(entity) => {
+                commands
+                    .entity(*entity)
+                    .insert(chunk)
+                    .insert(materials.for_kind(kind));
+            }
+            None => {
+                let entity = commands
+                    .spawn_bundle(PbrBundle {
+                        mesh: chunk.levels[0].clone(),
+                        material: materials.for_kind(kind),
+                        visibility: Visibility { is_visible: false },
+                        ..default()
+                    })
+                    .insert(chunk)
+                    .id();
+                chunks.entities.insert(key, entity);
+            }
+        }
+    }
+}
+
+/// Shows the individual tiles of chunks close to the camera and the simplified chunk meshes for
+/// the ones further away.
+pub(super) fn update_lod(
+    camera_query: Query<&GlobalTransform, With<CurrentCameraTag>>,
+    mut chunk_query: Query<(&HexChunk, &mut Handle<Mesh>, &mut Visibility), Without<ChunkMember>>,
+    mut tile_query: Query<(&ChunkMember, &mut Visibility), Without<HexChunk>>,
+) {
+    let camera = match camera_query.get_single() {
+        Ok(transform) => transform.translation(),
+        Err(_) => return,
+    };
+
+    let mut detailed = HashSet::new();
+
+    for (chunk, mut mesh, mut visibility) in chunk_query.iter_mut() {
+        let level = lod_level(camera.distance(chunk.centre), &LOD_DISTANCES);
+        let is_visible = level > 0;
+
+        if is_visible {
+            let handle = &chunk.levels[level - 1];
+            if *mesh != *handle {
+                *mesh = handle.clone();
+            }
+        } else {
+            detailed.insert(chunk.key);
+        }
+
+        if visibility.is_visible != is_visible {
+            visibility.is_visible = is_visible;
+        }
+    }
+
+    for (member, mut visibility) in tile_query.iter_mut() {
+        let is_visible = detailed.contains(&member.0);
+        if visibility.is_visible != is_visible {
+            visibility.is_visible = is_visible;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lod_level_increases_with_distance() {
+        assert_eq!(lod_level(0.0, &LOD_DISTANCES), 0);
+        assert_eq!(lod_level(29.9, &LOD_DISTANCES), 0);
+        assert_eq!(lod_level(30.0, &LOD_DISTANCES), 1);
+        assert_eq!(lod_level(59.9, &LOD_DISTANCES), 1);
+        assert_eq!(lod_level(1000.0, &LOD_DISTANCES), 2);
+    }
+
+    #[test]
+    fn chunk_keys_group_offset_blocks() {
+        let key = |col, row| chunk_key(Axial::from(Offset::new(col, row)).into());
+
+        assert_eq!(key(0, 0), IVec2::ZERO);
+        assert_eq!(key(7, 7), IVec2::ZERO);
+        assert_eq!(key(8, 0), IVec2::new(1, 0));
+        assert_eq!(key(-1, -8), IVec2::new(-1, -1));
+    }
+}
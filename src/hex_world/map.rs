@@ -0,0 +1,228 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::coordinates::Cube;
+
+/// Data of a single tile, independent of how it is rendered.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TileData {
+    /// Index into `HexWorldSettings::tile_colors`.
+    pub kind: u32,
+    /// Height of the tile top above the default tile height.
+    pub elevation: f32,
+    /// Game specific values, saved and loaded with the map but otherwise left alone.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "BTreeMap::is_empty")
+    )]
+    pub custom: BTreeMap<String, String>,
+}
+
+/// All tiles of the world by coordinate. `HexWorld` spawns, updates and despawns tile entities
+/// to match it, so editing the map is done by changing this resource.
+#[derive(Clone, Debug, Default)]
+pub struct HexMap {
+    tiles: HashMap<Cube, TileData>,
+    changed: HashSet<Cube>,
+}
+
+impl HexMap {
+    pub fn get(&self, cube: Cube) -> Option<&TileData> {
+        self.tiles.get(&cube)
+    }
+
+    pub fn contains(&self, cube: Cube) -> bool {
+        self.tiles.contains_key(&cube)
+    }
+
+    pub fn len(&self) -> usize {
+        self.tiles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Cube, &TileData)> + '_ {
+        self.tiles.iter().map(|(cube, tile)| (*cube, tile))
+    }
+
+    /// Puts `tile` at `cube`, or removes the tile there for `None`. Returns the previous tile.
+    pub fn set(&mut self, cube: Cube, tile: Option<TileData>) -> Option<TileData> {
+        let previous = match &tile {
+            Some(tile) => self.tiles.insert(cube, tile.clone()),
+            None => self.tiles.remove(&cube),
+        };
+
+        if previous != tile {
+            self.changed.insert(cube);
+        }
+
+        previous
+    }
+
+    pub fn insert(&mut self, cube: Cube, tile: TileData) -> Option<TileData> {
+        self.set(cube, Some(tile))
+    }
+
+    pub fn remove(&mut self, cube: Cube) -> Option<TileData> {
+        self.set(cube, None)
+    }
+
+    /// Replaces all tiles, e.g. when loading a map.
+    pub fn replace<I>(&mut self, tiles: I)
+    where
+        I: IntoIterator<Item = (Cube, TileData)>,
+    {
+        let tiles: HashMap<_, _> = tiles.into_iter().collect();
+
+        self.changed.extend(self.tiles.keys().copied());
+        self.changed.extend(tiles.keys().copied());
+        self.tiles = tiles;
+    }
+
+    pub fn apply(&mut self, cube: Cube, action: &EditAction) -> Option<TileData> {
+        let tile = action.apply(self.get(cube).cloned());
+        self.set(cube, tile)
+    }
+
+    /// Coordinates changed since the last call, for updating the tile entities.
+    pub(super) fn take_changes(&mut self) -> Vec<Cube> {
+        self.changed.drain().collect()
+    }
+}
+
+/// Change made to the tiles under a brush.
+#[derive(Clone, Debug, PartialEq)]
+pub enum EditAction {
+    /// Places the tile on hexes that have none.
+    Add(TileData),
+    Remove,
+    SetKind(u32),
+    SetElevation(f32),
+    /// Changes the elevation by the given amount, negative values lower the tile.
+    Raise(f32),
+}
+
+impl EditAction {
+    pub fn apply(&self, tile: Option<TileData>) -> Option<TileData> {
+        match (self, tile) {
+            (EditAction::Add(new), None) => Some(new.clone()),
+            (EditAction::Remove, _) => None,
+            (EditAction::SetKind(kind), Some(tile)) => Some(TileData {
+                kind: *kind,
+                ..tile
+            }),
+            (EditAction::SetElevation(elevation), Some(tile)) => Some(TileData {
+                elevation: *elevation,
+                ..tile
+            }),
+            (EditAction::Raise(amount), Some(tile)) => Some(TileData {
+                elevation: tile.elevation + amount,
+                ..tile
+            }),
+            (_, tile) => tile,
+        }
+    }
+}
+
+/// Hexes affected by an edit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Brush {
+    Single,
+    Radius(u32),
+    /// Straight line from where the stroke started to where it ends.
+    Line,
+}
+
+impl Brush {
+    pub fn footprint(self, start: Cube, target: Cube) -> Vec<Cube> {
+        match self {
+            Brush::Single => vec![target],
+            Brush::Radius(radius) => target.range(radius).collect(),
+            Brush::Line => start.line_to(target),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cube(q: i32) -> Cube {
+        Cube::new(q, 0, -q).unwrap()
+    }
+
+    #[test]
+    fn changes_are_tracked_once() {
+        let mut map = HexMap::default();
+
+        map.insert(cube(0), TileData::default());
+        map.insert(cube(0), TileData::default());
+        map.insert(cube(1), TileData::default());
+        map.remove(cube(1));
+
+        let mut changes = map.take_changes();
+        changes.sort_by_key(|cube| cube.q);
+        assert_eq!(changes, vec![cube(0), cube(1)]);
+        assert!(map.take_changes().is_empty());
+
+        // Setting the same tile again is no change.
+        map.insert(cube(0), TileData::default());
+        assert!(map.take_changes().is_empty());
+    }
+
+    #[test]
+    fn replace_changes_old_and_new_tiles() {
+        let mut map = HexMap::default();
+        map.insert(cube(0), TileData::default());
+        map.take_changes();
+
+        map.replace([(cube(1), TileData::default())]);
+
+        assert!(!map.contains(cube(0)));
+        assert!(map.contains(cube(1)));
+        assert_eq!(map.take_changes().len(), 2);
+    }
+
+    #[test]
+    fn actions_edit_tiles() {
+        let tile = TileData {
+            kind: 1,
+            elevation: 0.5,
+            ..TileData::default()
+        };
+
+        assert_eq!(
+            EditAction::Add(tile.clone()).apply(None),
+            Some(tile.clone())
+        );
+        assert_eq!(
+            EditAction::Add(TileData::default()).apply(Some(tile.clone())),
+            Some(tile.clone())
+        );
+        assert_eq!(EditAction::Remove.apply(Some(tile.clone())), None);
+        assert_eq!(
+            EditAction::SetKind(3)
+                .apply(Some(tile.clone()))
+                .unwrap()
+                .kind,
+            3
+        );
+        assert_eq!(
+            EditAction::Raise(-0.25)
+                .apply(Some(tile))
+                .unwrap()
+                .elevation,
+            0.25
+        );
+        assert_eq!(EditAction::SetElevation(2.0).apply(None), None);
+    }
+
+    #[test]
+    fn brushes_cover_expected_hexes() {
+        assert_eq!(Brush::Single.footprint(cube(0), cube(3)), vec![cube(3)]);
+        assert_eq!(Brush::Radius(1).footprint(cube(0), cube(3)).len(), 7);
+        assert_eq!(Brush::Line.footprint(cube(0), cube(3)).len(), 4);
+    }
+}
@@ -0,0 +1,671 @@
+use std::{collections::BTreeMap, fs, io, path::Path};
+
+use thiserror::Error;
+
+use crate::coordinates::{Axial, Cube, Offset};
+
+use super::map::{HexMap, TileData};
+use super::settings::MapShape;
+
+/// Version written by `MapFile`. Changes to the format increase it, older maps are brought up
+/// to date when they are read, see `VersionedMap`.
+///
+/// - 1: metadata, shape and tiles
+/// - 2: adds the tile height the map was made with
+pub const MAP_VERSION: u32 = 2;
+
+/// Tile height of the default settings while version 1 was current. Version 1 maps did not
+/// store it, so they are assumed to use it.
+const VERSION_1_TILE_HEIGHT: f32 = 0.25;
+
+/// First bytes of every binary map.
+const MAGIC: &[u8; 4] = b"HEXM";
+
+#[derive(Debug, Error)]
+pub enum MapFileError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[cfg(feature = "serde")]
+    #[error(transparent)]
+    RonRead(#[from] ron::error::SpannedError),
+    #[cfg(feature = "serde")]
+    #[error(transparent)]
+    RonWrite(#[from] ron::Error),
+    #[cfg(feature = "serde")]
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error("{0:?} maps need the serde feature")]
+    FormatUnavailable(MapFormat),
+    #[error("File is not a binary hex map")]
+    NotAMap,
+    #[error("Map version {0} is not supported")]
+    UnsupportedVersion(u32),
+    #[error("Map data ends unexpectedly")]
+    UnexpectedEnd,
+    #[error("Invalid map data: {0}")]
+    Invalid(&'static str),
+}
+
+/// Encoding of a map file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MapFormat {
+    /// Human readable, for editing maps by hand.
+    Ron,
+    Json,
+    /// Compact, for large maps.
+    Binary,
+}
+
+impl MapFormat {
+    /// Format for a file name, `.ron` and `.json` files are text, everything else binary.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("ron") => MapFormat::Ron,
+            Some("json") => MapFormat::Json,
+            _ => MapFormat::Binary,
+        }
+    }
+}
+
+/// Information about a map that is not needed to play it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MapMetadata {
+    pub name: String,
+    pub author: String,
+    pub description: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TileRecord {
+    pub cube: Cube,
+    pub tile: TileData,
+}
+
+/// Contents of a saved map.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MapFile {
+    pub version: u32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub metadata: MapMetadata,
+    /// `HexWorldSettings::tile_height` the map was saved with. Tile elevations are relative to
+    /// it, so loading the map restores it.
+    pub tile_height: f32,
+    /// Shape the map was created with. The tiles are the ones in `tiles`, which may differ
+    /// from it after editing.
+    pub shape: MapShape,
+    pub tiles: Vec<TileRecord>,
+}
+
+/// A map as written in version 1, before the tile height was stored.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(rename = "MapFile")
+)]
+struct MapFileV1 {
+    #[cfg_attr(feature = "serde", serde(default))]
+    metadata: MapMetadata,
+    shape: MapShape,
+    tiles: Vec<TileRecord>,
+}
+
+impl From<MapFileV1> for MapFile {
+    fn from(map: MapFileV1) -> Self {
+        Self {
+            version: MAP_VERSION,
+            metadata: map.metadata,
+            tile_height: VERSION_1_TILE_HEIGHT,
+            shape: map.shape,
+            tiles: map.tiles,
+        }
+    }
+}
+
+/// A map decoded in the layout of the version it was saved in.
+enum VersionedMap {
+    V1(MapFileV1),
+    V2(MapFile),
+}
+
+impl VersionedMap {
+    /// Brings the map up to `MAP_VERSION` one version at a time.
+    fn migrate(self) -> MapFile {
+        match self {
+            VersionedMap::V1(map) => VersionedMap::V2(map.into()).migrate(),
+            VersionedMap::V2(map) => map,
+        }
+    }
+}
+
+/// Just the version of a text map, read before the rest so every version can be decoded with
+/// its own layout.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+#[serde(rename = "MapFile")]
+struct VersionTag {
+    version: u32,
+}
+
+impl MapFile {
+    pub fn new(map: &HexMap, shape: MapShape, tile_height: f32, metadata: MapMetadata) -> Self {
+        let mut tiles: Vec<_> = map
+            .iter()
+            .map(|(cube, tile)| TileRecord {
+                cube,
+                tile: tile.clone(),
+            })
+            .collect();
+        // Sorted, so saving the same map twice gives the same file.
+        tiles.sort_by_key(|record| (record.cube.r, record.cube.q));
+
+        Self {
+            version: MAP_VERSION,
+            metadata,
+            tile_height,
+            shape,
+            tiles,
+        }
+    }
+
+    pub fn into_tiles(self) -> impl Iterator<Item = (Cube, TileData)> {
+        self.tiles
+            .into_iter()
+            .map(|record| (record.cube, record.tile))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), MapFileError> {
+        let path = path.as_ref();
+        let bytes = match MapFormat::from_path(path) {
+            MapFormat::Ron => self.to_ron()?.into_bytes(),
+            MapFormat::Json => self.to_json()?.into_bytes(),
+            MapFormat::Binary => self.to_bytes(),
+        };
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, bytes)?;
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, MapFileError> {
+        let path = path.as_ref();
+        let bytes = fs::read(path)?;
+        let text = |bytes| {
+            String::from_utf8(bytes).map_err(|_| MapFileError::Invalid("text map is not UTF-8"))
+        };
+
+        match MapFormat::from_path(path) {
+            MapFormat::Ron => Self::from_ron(&text(bytes)?),
+            MapFormat::Json => Self::from_json(&text(bytes)?),
+            MapFormat::Binary => Self::from_bytes(&bytes),
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn to_ron(&self) -> Result<String, MapFileError> {
+        Ok(ron::ser::to_string_pretty(
+            self,
+            ron::ser::PrettyConfig::default(),
+        )?)
+    }
+
+    #[cfg(not(feature = "serde"))]
+    pub fn to_ron(&self) -> Result<String, MapFileError> {
+        Err(MapFileError::FormatUnavailable(MapFormat::Ron))
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn from_ron(text: &str) -> Result<Self, MapFileError> {
+        let map = match ron::from_str::<VersionTag>(text)?.version {
+            1 => VersionedMap::V1(ron::from_str(text)?),
+            2 => VersionedMap::V2(ron::from_str(text)?),
+            version => return Err(MapFileError::UnsupportedVersion(version)),
+        };
+        Ok(map.migrate())
+    }
+
+    #[cfg(not(feature = "serde"))]
+    pub fn from_ron(_text: &str) -> Result<Self, MapFileError> {
+        Err(MapFileError::FormatUnavailable(MapFormat::Ron))
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> Result<String, MapFileError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    #[cfg(not(feature = "serde"))]
+    pub fn to_json(&self) -> Result<String, MapFileError> {
+        Err(MapFileError::FormatUnavailable(MapFormat::Json))
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn from_json(text: &str) -> Result<Self, MapFileError> {
+        let map = match serde_json::from_str::<VersionTag>(text)?.version {
+            1 => VersionedMap::V1(serde_json::from_str(text)?),
+            2 => VersionedMap::V2(serde_json::from_str(text)?),
+            version => return Err(MapFileError::UnsupportedVersion(version)),
+        };
+        Ok(map.migrate())
+    }
+
+    #[cfg(not(feature = "serde"))]
+    pub fn from_json(_text: &str) -> Result<Self, MapFileError> {
+        Err(MapFileError::FormatUnavailable(MapFormat::Json))
+    }
+
+    /// Encodes the map in the binary format, little endian:
+    ///
+    /// - `HEXM`, version as u32
+    /// - metadata: name, author and description
+    /// - tile height as f32
+    /// - shape: u8 tag, then the corners for rectangles, centre and radius for hexagons or the
+    ///   count and coordinates of custom shapes
+    /// - tile count as u32, then per tile q and r as i32, kind as u32, elevation as f32 and the
+    ///   custom fields as a u32 count and key value pairs
+    ///
+    /// Strings are a u32 length and UTF-8 bytes, coordinates are axial q and r.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer = Writer(Vec::with_capacity(32 + 24 * self.tiles.len()));
+
+        writer.0.extend_from_slice(MAGIC);
+        writer.u32(MAP_VERSION);
+
+        writer.str(&self.metadata.name);
+        writer.str(&self.metadata.author);
+        writer.str(&self.metadata.description);
+        writer.f32(self.tile_height);
+
+        match &self.shape {
+            MapShape::Rectangle { min, max } => {
+                writer.u8(0);
+                for offset in [min, max] {
+                    writer.i32(offset.col);
+                    writer.i32(offset.row);
+                }
+            }
+            MapShape::Hexagon { centre, radius } => {
+                writer.u8(1);
+                writer.cube(*centre);
+                writer.u32(*radius);
+            }
+            MapShape::Custom(cubes) => {
+                writer.u8(2);
+                writer.len(cubes.len());
+                for cube in cubes {
+                    writer.cube(*cube);
+                }
+            }
+        }
+
+        writer.len(self.tiles.len());
+        for TileRecord { cube, tile } in &self.tiles {
+            writer.cube(*cube);
+            writer.u32(tile.kind);
+            writer.f32(tile.elevation);
+            writer.len(tile.custom.len());
+            for (key, value) in &tile.custom {
+                writer.str(key);
+                writer.str(value);
+            }
+        }
+
+        writer.0
+    }
+
+    /// Decodes a map written by `to_bytes` in this or an older version. Version 1 maps lack
+    /// the tile height.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, MapFileError> {
+        let mut reader = Reader(bytes);
+
+        if reader.take(MAGIC.len()).ok() != Some(MAGIC.as_slice()) {
+            return Err(MapFileError::NotAMap);
+        }
+
+        let map = match reader.u32()? {
+            1 => {
+                let metadata = reader.metadata()?;
+                let shape = reader.shape()?;
+                let tiles = reader.tiles()?;
+                VersionedMap::V1(MapFileV1 {
+                    metadata,
+                    shape,
+                    tiles,
+                })
+            }
+            2 => {
+                let metadata = reader.metadata()?;
+                let tile_height = reader.f32()?;
+                let shape = reader.shape()?;
+                let tiles = reader.tiles()?;
+                VersionedMap::V2(MapFile {
+                    version: 2,
+                    metadata,
+                    tile_height,
+                    shape,
+                    tiles,
+                })
+            }
+            version => return Err(MapFileError::UnsupportedVersion(version)),
+        };
+
+        if !reader.0.is_empty() {
+            return Err(MapFileError::Invalid("data after the last tile"));
+        }
+
+        Ok(map.migrate())
+    }
+}
+
+struct Writer(Vec<u8>);
+
+impl Writer {
+    fn u8(&mut self, value: u8) {
+        self.0.push(value);
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn i32(&mut self, value: i32) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn f32(&mut self, value: f32) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn len(&mut self, len: usize) {
+        self.u32(len as u32);
+    }
+
+    fn str(&mut self, value: &str) {
+        self.len(value.len());
+        self.0.extend_from_slice(value.as_bytes());
+    }
+
+    fn cube(&mut self, cube: Cube) {
+        let axial = Axial::from(cube);
+        self.i32(axial.q);
+        self.i32(axial.r);
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], MapFileError> {
+        if self.0.len() < count {
+            return Err(MapFileError::UnexpectedEnd);
+        }
+
+        let (taken, rest) = self.0.split_at(count);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], MapFileError> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> Result<u8, MapFileError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, MapFileError> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn i32(&mut self) -> Result<i32, MapFileError> {
+        Ok(i32::from_le_bytes(self.array()?))
+    }
+
+    fn f32(&mut self) -> Result<f32, MapFileError> {
+        Ok(f32::from_le_bytes(self.array()?))
+    }
+
+    /// Count of items that take at least `item_size` bytes each, checked against the remaining
+    /// data so a corrupt count fails instead of allocating huge buffers.
+    fn len(&mut self, item_size: usize) -> Result<usize, MapFileError> {
+        let len = self.u32()? as usize;
+        if len.saturating_mul(item_size) > self.0.len() {
+            return Err(MapFileError::UnexpectedEnd);
+        }
+        Ok(len)
+    }
+
+    fn string(&mut self) -> Result<String, MapFileError> {
+        let len = self.len(1)?;
+        String::from_utf8(self.take(len)?.to_vec())
+            .map_err(|_| MapFileError::Invalid("string is not UTF-8"))
+    }
+
+    fn cube(&mut self) -> Result<Cube, MapFileError> {
+        Ok(Cube::from(Axial::new(self.i32()?, self.i32()?)))
+    }
+
+    fn metadata(&mut self) -> Result<MapMetadata, MapFileError> {
+        Ok(MapMetadata {
+            name: self.string()?,
+            author: self.string()?,
+            description: self.string()?,
+        })
+    }
+
+    fn shape(&mut self) -> Result<MapShape, MapFileError> {
+        Ok(match self.u8()? {
+            0 => MapShape::Rectangle {
+                min: Offset::new(self.i32()?, self.i32()?),
+                max: Offset::new(self.i32()?, self.i32()?),
+            },
+            1 => MapShape::Hexagon {
+                centre: self.cube()?,
+                radius: self.u32()?,
+            },
+            2 => {
+                let count = self.len(8)?;
+                MapShape::Custom((0..count).map(|_| self.cube()).collect::<Result<_, _>>()?)
+            }
+            _ => return Err(MapFileError::Invalid("unknown shape")),
+        })
+    }
+
+    fn tiles(&mut self) -> Result<Vec<TileRecord>, MapFileError> {
+        let count = self.len(16)?;
+        let mut tiles = Vec::with_capacity(count);
+        for _ in 0..count {
+            let cube = self.cube()?;
+            let kind = self.u32()?;
+            let elevation = self.f32()?;
+
+            let mut custom = BTreeMap::new();
+            for _ in 0..self.len(8)? {
+                custom.insert(self.string()?, self.string()?);
+            }
+
+            tiles.push(TileRecord {
+                cube,
+                tile: TileData {
+                    kind,
+                    elevation,
+                    custom,
+                },
+            });
+        }
+        Ok(tiles)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example() -> MapFile {
+        let mut map = HexMap::default();
+        for (i, cube) in Cube::origin().range(1).enumerate() {
+            let mut tile = TileData {
+                kind: i as u32 % 3,
+                elevation: 0.25 * i as f32,
+                ..TileData::default()
+            };
+            if i == 2 {
+                tile.custom.insert("spawn".into(), "player".into());
+            }
+            map.insert(cube, tile);
+        }
+
+        MapFile::new(
+            &map,
+            MapShape::Hexagon {
+                centre: Cube::origin(),
+                radius: 1,
+            },
+            0.3,
+            MapMetadata {
+                name: "Island".into(),
+                author: "Designer".into(),
+                description: String::new(),
+            },
+        )
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        let file = example();
+
+        let bytes = file.to_bytes();
+
+        assert_eq!(MapFile::from_bytes(&bytes).unwrap(), file);
+    }
+
+    /// `example` as it would have been read from a version 1 map.
+    fn example_from_version_1() -> MapFile {
+        MapFile {
+            tile_height: VERSION_1_TILE_HEIGHT,
+            ..example()
+        }
+    }
+
+    #[test]
+    fn binary_version_1_is_migrated() {
+        let file = example();
+        let mut bytes = file.to_bytes();
+        // Version 1 has no tile height after the metadata.
+        let metadata = &file.metadata;
+        let metadata_end = MAGIC.len()
+            + 4
+            + [&metadata.name, &metadata.author, &metadata.description]
+                .map(|text| 4 + text.len())
+                .iter()
+                .sum::<usize>();
+        bytes.drain(metadata_end..metadata_end + 4);
+        bytes[4..8].copy_from_slice(&1u32.to_le_bytes());
+
+        assert_eq!(
+            MapFile::from_bytes(&bytes).unwrap(),
+            example_from_version_1()
+        );
+    }
+
+    #[test]
+    fn binary_rejects_invalid_data() {
+        let mut bytes = example().to_bytes();
+
+        assert!(matches!(
+            MapFile::from_bytes(b"PNG..."),
+            Err(MapFileError::NotAMap)
+        ));
+        assert!(matches!(
+            MapFile::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(MapFileError::UnexpectedEnd)
+        ));
+
+        bytes[4..8].copy_from_slice(&(MAP_VERSION + 1).to_le_bytes());
+        assert!(matches!(
+            MapFile::from_bytes(&bytes),
+            Err(MapFileError::UnsupportedVersion(_))
+        ));
+    }
+
+    #[test]
+    fn text_map_must_be_utf8() {
+        let path = std::env::temp_dir().join(format!("map-{}.ron", std::process::id()));
+        fs::write(&path, b"(version: 1, name: \"\xff\")").unwrap();
+
+        let result = MapFile::load(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert!(matches!(result, Err(MapFileError::Invalid(_))));
+    }
+
+    #[test]
+    fn format_follows_extension() {
+        assert_eq!(MapFormat::from_path(Path::new("a.ron")), MapFormat::Ron);
+        assert_eq!(MapFormat::from_path(Path::new("a.json")), MapFormat::Json);
+        assert_eq!(
+            MapFormat::from_path(Path::new("maps/a.hexmap")),
+            MapFormat::Binary
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn text_round_trip() {
+        let file = example();
+
+        assert_eq!(MapFile::from_ron(&file.to_ron().unwrap()).unwrap(), file);
+        assert_eq!(MapFile::from_json(&file.to_json().unwrap()).unwrap(), file);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn text_version_1_is_migrated() {
+        let ron = example()
+            .to_ron()
+            .unwrap()
+            .lines()
+            .filter(|line| !line.trim_start().starts_with("tile_height:"))
+            .collect::<Vec<_>>()
+            .join("\n")
+            .replace("version: 2", "version: 1");
+        assert_eq!(MapFile::from_ron(&ron).unwrap(), example_from_version_1());
+
+        let mut json: serde_json::Value =
+            serde_json::from_str(&example().to_json().unwrap()).unwrap();
+        json["version"] = 1.into();
+        json.as_object_mut().unwrap().remove("tile_height");
+        assert_eq!(
+            MapFile::from_json(&json.to_string()).unwrap(),
+            example_from_version_1()
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn text_optional_fields_default_to_empty() {
+        let text = r#"{
+            "version": 2,
+            "tile_height": 0.25,
+            "shape": { "Custom": [{ "q": 0, "r": 0, "s": 0 }] },
+            "tiles": [{ "cube": { "q": 0, "r": 0, "s": 0 }, "tile": { "kind": 2, "elevation": 1.0 } }]
+        }"#;
+
+        let file = MapFile::from_json(text).unwrap();
+
+        assert_eq!(file.metadata, MapMetadata::default());
+        assert_eq!(file.tiles[0].tile.kind, 2);
+        assert!(file.tiles[0].tile.custom.is_empty());
+
+        let newer = text.replace(r#""version": 2"#, r#""version": 3"#);
+        assert!(matches!(
+            MapFile::from_json(&newer),
+            Err(MapFileError::UnsupportedVersion(3))
+        ));
+    }
+}
@@ -0,0 +1,137 @@
+use bevy::prelude::Vec3;
+use strum::IntoEnumIterator;
+
+use crate::coordinates::{Axial, Cube, Direction};
+
+/// Tile hit by a ray in `pick_hex`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HexHit {
+    pub cube: Cube,
+    /// World position where the ray hits the top or the side of the tile.
+    pub point: Vec3,
+    /// Distance from the ray origin in world units.
+    pub distance: f32,
+}
+
+/// First tile hit by a ray, treating every tile as a column reaching up to the height returned
+/// by `top`, or as empty where it returns `None`.
+///
+/// The ray is followed from hex to hex through the sides it leaves them by, so only the tiles
+/// along its way are looked at.
+pub fn pick_hex<F>(origin: Vec3, direction: Vec3, max_distance: f32, top: F) -> Option<HexHit>
+where
+    F: Fn(Cube) -> Option<f32>,
+{
+    let direction = direction.try_normalize()?;
+    let mut cube = Cube::from_world(origin);
+    let mut entry = 0.0;
+
+    while entry <= max_distance {
+        let centre = Vec3::from(Axial::from(cube));
+        let (exit, next) = Direction::iter()
+            .filter_map(|direction_to_next| {
+                // The side towards a neighbour lies halfway between the two centres.
+                let offset = Vec3::from(Axial::from(cube + direction_to_next)) - centre;
+                let normal = offset.normalize();
+                let speed = direction.dot(normal);
+
+                (speed > 0.0).then(|| {
+                    let exit = (0.5 * offset.length() - (origin - centre).dot(normal)) / speed;
+                    (exit, direction_to_next)
+                })
+            })
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .unwrap_or((f32::INFINITY, Direction::E));
+
+        if let Some(top) = top(cube) {
+            let hit = if origin.y + entry * direction.y <= top {
+                // The ray enters the hex below its top, so it hits the side of the tile.
+                Some(entry)
+            } else if direction.y < 0.0 {
+                let distance = (top - origin.y) / direction.y;
+                (distance <= exit).then_some(distance)
+            } else {
+                None
+            };
+
+            if let Some(distance) = hit.filter(|distance| *distance <= max_distance) {
+                return Some(HexHit {
+                    cube,
+                    point: origin + distance * direction,
+                    distance,
+                });
+            }
+        }
+
+        entry = exit;
+        cube = cube + next;
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flat(height: f32) -> impl Fn(Cube) -> Option<f32> {
+        move |cube: Cube| (cube.distance_to(Cube::origin()) <= 5).then_some(height)
+    }
+
+    #[test]
+    fn straight_down_hits_hex_below() {
+        for cube in Cube::origin().neighbours() {
+            let centre = Vec3::from(Axial::from(cube));
+
+            let hit = pick_hex(
+                centre + Vec3::new(0.1, 10.0, 0.1),
+                -Vec3::Y,
+                100.0,
+                flat(0.25),
+            )
+            .unwrap();
+
+            assert_eq!(hit.cube, cube);
+            assert!((hit.distance - 9.75).abs() < 1e-5);
+            assert!((hit.point.y - 0.25).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn slanted_ray_hits_top_plane() {
+        let origin = Vec3::new(-3.0, 3.0, 0.3);
+        let direction = Vec3::new(1.0, -1.0, 0.0);
+
+        let hit = pick_hex(origin, direction, 100.0, flat(0.0)).unwrap();
+
+        assert!(hit.point.abs_diff_eq(Vec3::new(0.0, 0.0, 0.3), 1e-5));
+        assert_eq!(hit.cube, Cube::from_world(hit.point));
+    }
+
+    #[test]
+    fn raised_tile_blocks_the_ray() {
+        let raised = Cube::origin() + Direction::W;
+        let top = |cube: Cube| Some(if cube == raised { 2.0 } else { 0.0 });
+
+        // Would hit the origin tile if the one in front of it was flat.
+        let origin = Vec3::new(-3.0, 3.0, 0.0);
+        let hit = pick_hex(origin, Vec3::new(1.0, -1.0, 0.0), 100.0, top).unwrap();
+
+        assert_eq!(hit.cube, raised);
+        assert!(hit.point.y <= 2.0 + 1e-5);
+    }
+
+    #[test]
+    fn missing_tiles_are_skipped() {
+        let top = |cube: Cube| (cube != Cube::origin()).then_some(0.0);
+
+        assert!(pick_hex(Vec3::new(0.0, 5.0, 0.0), -Vec3::Y, 100.0, top).is_none());
+    }
+
+    #[test]
+    fn ray_pointing_away_misses() {
+        assert!(pick_hex(Vec3::new(0.0, 5.0, 0.0), Vec3::Y, 100.0, flat(0.0)).is_none());
+        assert!(pick_hex(Vec3::new(0.0, 5.0, 0.0), Vec3::X, 100.0, flat(0.0)).is_none());
+        assert!(pick_hex(Vec3::new(0.0, 50.0, 0.0), -Vec3::Y, 10.0, flat(0.0)).is_none());
+    }
+}
@@ -0,0 +1,150 @@
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+
+use super::heightmap::{load_heightmap, save_heightmap};
+use super::history::EditHistory;
+use super::map::HexMap;
+use super::map_file::{MapFile, MapMetadata};
+use super::selection::HexSelection;
+use super::settings::HexWorldSettings;
+use super::tiled::load_tiled;
+
+/// Writes the current map to a file. The format follows the extension, see `MapFormat`.
+pub struct SaveMap(pub PathBuf);
+
+/// Replaces the current map with the one in a file. All tiles are updated, spawned or
+/// despawned to match it.
+pub struct LoadMap(pub PathBuf);
+
+/// Replaces the current map with tiles built from a heightmap image, using
+/// `HexWorldSettings::heightmap_settings`.
+pub struct ImportHeightmap(pub PathBuf);
+
+/// Writes the tile elevations to a grayscale image, using
+/// `HexWorldSettings::heightmap_settings`.
+pub struct ExportHeightmap(pub PathBuf);
+
+/// Replaces the current map with the tiles of a hexagonal Tiled map, using
+/// `HexWorldSettings::tiled_settings`.
+pub struct ImportTiled(pub PathBuf);
+
+pub(super) fn save_maps(
+    mut events: EventReader<SaveMap>,
+    map: Res<HexMap>,
+    metadata: Res<MapMetadata>,
+    settings: Res<HexWorldSettings>,
+) {
+    for SaveMap(path) in events.iter() {
+        let file = MapFile::new(
+            &map,
+            settings.shape.clone(),
+            settings.tile_height,
+            metadata.clone(),
+        );
+
+        match file.save(path) {
+            Ok(()) => info!("Saved map to {}", path.display()),
+            Err(error) => error!("Could not save map to {}: {}", path.display(), error),
+        }
+    }
+}
+
+/// Loads maps before the tiles are synced, so the new map shows up in the same frame. Edits and
+/// selection of the old map are dropped.
+pub(super) fn load_maps(
+    mut events: EventReader<LoadMap>,
+    mut map: ResMut<HexMap>,
+    mut metadata: ResMut<MapMetadata>,
+    mut settings: ResMut<HexWorldSettings>,
+    mut history: ResMut<EditHistory>,
+    mut selection: ResMut<HexSelection>,
+) {
+    for LoadMap(path) in events.iter() {
+        let file = match MapFile::load(path) {
+            Ok(file) => file,
+            Err(error) => {
+                error!("Could not load map from {}: {}", path.display(), error);
+                continue;
+            }
+        };
+
+        info!(
+            "Loaded map {:?} from {}",
+            file.metadata.name,
+            path.display()
+        );
+
+        *metadata = file.metadata.clone();
+        settings.shape = file.shape.clone();
+        settings.tile_height = file.tile_height;
+        history.clear();
+        selection.clear();
+        map.replace(file.into_tiles());
+    }
+}
+
+pub(super) fn export_heightmaps(
+    mut events: EventReader<ExportHeightmap>,
+    map: Res<HexMap>,
+    settings: Res<HexWorldSettings>,
+) {
+    for ExportHeightmap(path) in events.iter() {
+        match save_heightmap(&map, path, &settings.heightmap_settings) {
+            Ok(()) => info!("Exported heightmap to {}", path.display()),
+            Err(error) => error!(
+                "Could not export heightmap to {}: {}",
+                path.display(),
+                error
+            ),
+        }
+    }
+}
+
+pub(super) fn import_heightmaps(
+    mut events: EventReader<ImportHeightmap>,
+    mut map: ResMut<HexMap>,
+    mut history: ResMut<EditHistory>,
+    mut selection: ResMut<HexSelection>,
+    settings: Res<HexWorldSettings>,
+) {
+    for ImportHeightmap(path) in events.iter() {
+        match load_heightmap(path, &settings.heightmap_settings) {
+            Ok(tiles) => {
+                info!("Imported {} tiles from {}", tiles.len(), path.display());
+                history.clear();
+                selection.clear();
+                map.replace(tiles);
+            }
+            Err(error) => error!(
+                "Could not import heightmap from {}: {}",
+                path.display(),
+                error
+            ),
+        }
+    }
+}
+
+pub(super) fn import_tiled_maps(
+    mut events: EventReader<ImportTiled>,
+    mut map: ResMut<HexMap>,
+    mut history: ResMut<EditHistory>,
+    mut selection: ResMut<HexSelection>,
+    settings: Res<HexWorldSettings>,
+) {
+    for ImportTiled(path) in events.iter() {
+        match load_tiled(path, &settings.tiled_settings) {
+            Ok(tiles) => {
+                info!("Imported {} tiles from {}", tiles.len(), path.display());
+                history.clear();
+                selection.clear();
+                map.replace(tiles);
+            }
+            Err(error) => error!(
+                "Could not import Tiled map from {}: {}",
+                path.display(),
+                error
+            ),
+        }
+    }
+}
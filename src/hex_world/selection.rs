@@ -0,0 +1,263 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+
+use crate::coordinates::{Axial, Cube, Offset};
+
+use super::editor::MapEditor;
+use super::events::{HexClicked, HexDrag, HexDragEnd, HexDragStart};
+use super::hex_world::Hexagon;
+use super::index::HexEntityIndex;
+use super::map::HexMap;
+use super::tiles::TileMaterials;
+
+/// How a set of hexes is combined with the current selection.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SelectionMode {
+    Replace,
+    Add,
+    Toggle,
+}
+
+/// Shape selected by dragging from one hex to another.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SelectionTool {
+    /// All hexes on the straight line between both ends.
+    #[default]
+    Line,
+    /// All hexes around the start of the drag up to the current distance.
+    Radius,
+    /// All hexes in the offset coordinate rectangle spanned by both ends.
+    Box,
+}
+
+/// Hexes selected by the user. Changes to it are reported as `HexSelectionChanged` events and
+/// selected tiles are drawn with `TileMaterials::selected`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HexSelection {
+    selected: HashSet<Cube>,
+}
+
+impl HexSelection {
+    pub fn contains(&self, cube: Cube) -> bool {
+        self.selected.contains(&cube)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Cube> + '_ {
+        self.selected.iter().copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.selected.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.selected.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.selected.clear();
+    }
+
+    pub fn apply<I>(&mut self, cubes: I, mode: SelectionMode)
+    where
+        I: IntoIterator<Item = Cube>,
+    {
+        match mode {
+            SelectionMode::Replace => self.selected = cubes.into_iter().collect(),
+            SelectionMode::Add => self.selected.extend(cubes),
+            SelectionMode::Toggle => {
+                // A shape may contain a hex more than once, which must not toggle it back.
+                for cube in cubes.into_iter().collect::<HashSet<_>>() {
+                    if !self.selected.remove(&cube) {
+                        self.selected.insert(cube);
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn select_line(&mut self, from: Cube, to: Cube, mode: SelectionMode) {
+        self.apply(from.line_to(to), mode);
+    }
+
+    pub fn select_radius(&mut self, centre: Cube, radius: u32, mode: SelectionMode) {
+        self.apply(centre.range(radius), mode);
+    }
+
+    pub fn select_box(&mut self, corner: Cube, opposite: Cube, mode: SelectionMode) {
+        let (a, b) = (Offset::from(corner), Offset::from(opposite));
+        let cols = a.col.min(b.col)..=a.col.max(b.col);
+        let rows = a.row.min(b.row)..=a.row.max(b.row);
+
+        self.apply(
+            rows.flat_map(|row| {
+                cols.clone()
+                    .map(move |col| Cube::from(Axial::from(Offset::new(col, row))))
+            }),
+            mode,
+        );
+    }
+
+    fn select_shape(&mut self, tool: SelectionTool, from: Cube, to: Cube, mode: SelectionMode) {
+        match tool {
+            SelectionTool::Line => self.select_line(from, to, mode),
+            SelectionTool::Radius => self.select_radius(from, from.distance_to(to), mode),
+            SelectionTool::Box => self.select_box(from, to, mode),
+        }
+    }
+}
+
+/// Hexes that were added to and removed from `HexSelection` since the last event.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HexSelectionChanged {
+    pub added: Vec<Cube>,
+    pub removed: Vec<Cube>,
+}
+
+fn selection_mode(keys: &Input<KeyCode>) -> SelectionMode {
+    if keys.any_pressed([KeyCode::LControl, KeyCode::RControl]) {
+        SelectionMode::Toggle
+    } else if keys.any_pressed([KeyCode::LShift, KeyCode::RShift]) {
+        SelectionMode::Add
+    } else {
+        SelectionMode::Replace
+    }
+}
+
+/// Selects hexes with the left mouse button. Shift adds to the selection, control toggles, and
+/// dragging selects the shape of the current `SelectionTool`. The mouse edits the map instead
+/// while the `MapEditor` is enabled.
+#[allow(clippy::too_many_arguments)]
+pub(super) fn select_hexes(
+    keys: Res<Input<KeyCode>>,
+    tool: Res<SelectionTool>,
+    editor: Res<MapEditor>,
+    mut selection: ResMut<HexSelection>,
+    mut before_drag: Local<Option<HexSelection>>,
+    mut clicked: EventReader<HexClicked>,
+    mut drag_start: EventReader<HexDragStart>,
+    mut drag: EventReader<HexDrag>,
+    mut drag_end: EventReader<HexDragEnd>,
+) {
+    if editor.enabled {
+        *before_drag = None;
+        return;
+    }
+
+    let mode = selection_mode(&keys);
+
+    for event in clicked.iter().filter(|e| e.button == MouseButton::Left) {
+        selection.apply([event.cube], mode);
+    }
+
+    if drag_start.iter().any(|e| e.button == MouseButton::Left) {
+        *before_drag = Some(selection.clone());
+    }
+
+    // Every drag step selects the whole shape again, starting from the selection before the
+    // drag, so the shape can shrink as well as grow.
+    if let Some(event) = drag.iter().filter(|e| e.button == MouseButton::Left).last() {
+        if let Some(before) = before_drag.as_ref() {
+            let mut dragged = before.clone();
+            dragged.select_shape(*tool, event.start, event.cube, mode);
+            *selection = dragged;
+        }
+    }
+
+    if drag_end.iter().any(|e| e.button == MouseButton::Left) {
+        *before_drag = None;
+    }
+}
+
+pub(super) fn emit_selection_changes(
+    selection: Res<HexSelection>,
+    mut previous: Local<HashSet<Cube>>,
+    mut changes: EventWriter<HexSelectionChanged>,
+) {
+    if !selection.is_changed() {
+        return;
+    }
+
+    let added: Vec<_> = selection.selected.difference(&previous).copied().collect();
+    let removed: Vec<_> = previous.difference(&selection.selected).copied().collect();
+
+    if added.is_empty() && removed.is_empty() {
+        return;
+    }
+
+    *previous = selection.selected.clone();
+    changes.send(HexSelectionChanged { added, removed });
+}
+
+pub(super) fn highlight_selection(
+    materials: Res<TileMaterials>,
+    index: Res<HexEntityIndex>,
+    map: Res<HexMap>,
+    mut changes: EventReader<HexSelectionChanged>,
+    mut tile_query: Query<&mut Handle<StandardMaterial>, With<Hexagon>>,
+) {
+    let mut set_material = |cube: &Cube, material: Handle<StandardMaterial>| {
+        if let Some(mut tile_material) = index
+            .get(*cube)
+            .and_then(|entity| tile_query.get_mut(entity).ok())
+        {
+            *tile_material = material;
+        }
+    };
+
+    // Events are applied in order, so a hex selected and deselected again ends up normal.
+    for change in changes.iter() {
+        for cube in &change.added {
+            set_material(cube, materials.selected.clone());
+        }
+        for cube in &change.removed {
+            let kind = map.get(*cube).map_or(0, |tile| tile.kind);
+            set_material(cube, materials.for_kind(kind));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cube(q: i32, r: i32) -> Cube {
+        Cube::from(Axial::new(q, r))
+    }
+
+    #[test]
+    fn modes_combine_with_selection() {
+        let mut selection = HexSelection::default();
+
+        selection.apply([cube(0, 0), cube(1, 0)], SelectionMode::Replace);
+        assert_eq!(selection.len(), 2);
+
+        selection.apply([cube(2, 0)], SelectionMode::Replace);
+        assert_eq!(selection.iter().collect::<Vec<_>>(), vec![cube(2, 0)]);
+
+        selection.apply([cube(3, 0)], SelectionMode::Add);
+        assert!(selection.contains(cube(2, 0)) && selection.contains(cube(3, 0)));
+
+        selection.apply([cube(3, 0), cube(4, 0), cube(4, 0)], SelectionMode::Toggle);
+        assert!(!selection.contains(cube(3, 0)));
+        assert!(selection.contains(cube(4, 0)));
+        assert_eq!(selection.len(), 2);
+    }
+
+    #[test]
+    fn shapes_select_expected_hexes() {
+        let mut selection = HexSelection::default();
+
+        selection.select_line(cube(0, 0), cube(4, 0), SelectionMode::Replace);
+        assert_eq!(selection.len(), 5);
+
+        selection.select_radius(cube(0, 0), 2, SelectionMode::Replace);
+        assert_eq!(selection.len(), 19);
+
+        let corner = Cube::from(Axial::from(Offset::new(3, 2)));
+        selection.select_box(corner, cube(0, 0), SelectionMode::Replace);
+        assert_eq!(selection.len(), 4 * 3);
+        assert!(selection.contains(cube(0, 0)) && selection.contains(corner));
+    }
+}
@@ -0,0 +1,161 @@
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+
+use crate::coordinates::{Axial, Cube, Offset};
+
+use super::heightmap::HeightmapSettings;
+use super::tiled::TiledImportSettings;
+
+/// Hexes a world is made of.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MapShape {
+    /// All hexes between two corners in offset coordinates, both included.
+    Rectangle {
+        min: Offset,
+        max: Offset,
+    },
+    /// All hexes up to `radius` steps away from `centre`.
+    Hexagon {
+        centre: Cube,
+        radius: u32,
+    },
+    Custom(Vec<Cube>),
+}
+
+impl MapShape {
+    pub fn cubes(&self) -> Vec<Cube> {
+        match self {
+            MapShape::Rectangle { min, max } => (min.col..=max.col)
+                .flat_map(|col| {
+                    (min.row..=max.row)
+                        .map(move |row| Cube::from(Axial::from(Offset::new(col, row))))
+                })
+                .collect(),
+            MapShape::Hexagon { centre, radius } => centre.range(*radius).collect(),
+            MapShape::Custom(cubes) => cubes.clone(),
+        }
+    }
+}
+
+/// Parameters of the wave that runs through the tiles when one is clicked.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WaveSettings {
+    /// Upwards velocity a clicked tile gets.
+    pub click_impulse: f32,
+    /// Strength of the spring pulling a tile back to its rest height.
+    pub spring: f32,
+    pub damping: f32,
+    /// Strength of the spring between neighbouring tiles, which spreads the wave.
+    pub neighbour_spring: f32,
+    pub neighbour_damping: f32,
+    /// Part of the neighbour acceleration that is passed on, below 1 to let the wave fade.
+    pub transfer: f32,
+}
+
+impl Default for WaveSettings {
+    fn default() -> Self {
+        Self {
+            click_impulse: 30.0,
+            spring: 10.0,
+            damping: 1.5,
+            neighbour_spring: 1.0,
+            neighbour_damping: 0.1,
+            transfer: 0.9,
+        }
+    }
+}
+
+/// Configuration of the world spawned by `HexWorld`. Insert it before adding the plugin to
+/// replace the default map.
+#[derive(Clone, Debug)]
+pub struct HexWorldSettings {
+    pub shape: MapShape,
+    /// Image to build the map from instead of `shape`, see `HeightmapSettings`.
+    pub heightmap: Option<PathBuf>,
+    pub heightmap_settings: HeightmapSettings,
+    /// Tiled map to build the map from instead of `shape`, used if there is no `heightmap`.
+    /// See `TiledImportSettings`.
+    pub tiled: Option<PathBuf>,
+    pub tiled_settings: TiledImportSettings,
+    /// Distance from the centre of a tile to its corners. Tiles are laid out with a size of 0.5,
+    /// so anything smaller leaves a gap between them.
+    pub tile_size: f32,
+    /// Height of the tile prisms, the top of a tile is this far above its translation.
+    pub tile_height: f32,
+    pub bevel_width: f32,
+    pub bevel_segments: u8,
+    /// Colour of every tile kind, indexed by `TileData::kind`. Kinds past the end use the first.
+    pub tile_colors: Vec<Color>,
+    pub selected_color: Color,
+    pub metallic: f32,
+    pub wave: WaveSettings,
+    /// Gives every tile a rapier collider of its prism. Picking doesn't need them, so they are
+    /// off unless bodies are meant to collide with the tiles.
+    #[cfg(feature = "physics")]
+    pub tile_colliders: bool,
+}
+
+impl Default for HexWorldSettings {
+    fn default() -> Self {
+        Self {
+            shape: MapShape::Rectangle {
+                min: Offset::new(-25, -25),
+                max: Offset::new(25, 25),
+            },
+            heightmap: None,
+            heightmap_settings: HeightmapSettings::default(),
+            tiled: None,
+            tiled_settings: TiledImportSettings::default(),
+            tile_size: 0.49,
+            tile_height: 0.25,
+            bevel_width: 0.03,
+            bevel_segments: 3,
+            tile_colors: vec![
+                Color::rgb_u8(0, 98, 105),
+                Color::rgb_u8(76, 140, 60),
+                Color::rgb_u8(194, 178, 128),
+                Color::rgb_u8(120, 120, 120),
+            ],
+            selected_color: Color::rgb_u8(230, 160, 40),
+            metallic: 0.8,
+            wave: WaveSettings::default(),
+            #[cfg(feature = "physics")]
+            tile_colliders: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rectangle_contains_all_offsets() {
+        let shape = MapShape::Rectangle {
+            min: Offset::new(-1, 0),
+            max: Offset::new(2, 2),
+        };
+
+        let cubes = shape.cubes();
+
+        assert_eq!(cubes.len(), 4 * 3);
+        assert!(cubes.contains(&Cube::from(Axial::from(Offset::new(2, 2)))));
+    }
+
+    #[test]
+    fn hexagon_contains_all_hexes_in_range() {
+        let shape = MapShape::Hexagon {
+            centre: Cube::origin(),
+            radius: 2,
+        };
+
+        assert_eq!(shape.cubes().len(), 19);
+    }
+
+    #[test]
+    fn default_matches_original_map() {
+        assert_eq!(HexWorldSettings::default().shape.cubes().len(), 51 * 51);
+    }
+}
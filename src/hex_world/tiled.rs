@@ -0,0 +1,681 @@
+use std::{collections::HashMap, fs, io, path::Path, str::FromStr};
+
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::coordinates::{Axial, Cube};
+
+use super::map::TileData;
+
+/// Bits Tiled sets in a global tile id for flipped and rotated tiles.
+const GID_FLAGS: u32 = 0xF000_0000;
+
+#[derive(Debug, Error)]
+pub enum TiledError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Xml(#[from] roxmltree::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error("Only hexagonal maps can be imported, got {0}")]
+    NotHexagonal(String),
+    #[error("Missing or invalid {0}")]
+    Invalid(&'static str),
+    #[error("Tile data encoded as {0} is not supported")]
+    UnsupportedEncoding(String),
+    #[error("Map has no layer {0:?}")]
+    MissingLayer(String),
+}
+
+/// Axis along which every other row or column of a Tiled map is shifted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StaggerAxis {
+    /// Columns are shifted, the hexes have flat tops.
+    X,
+    /// Rows are shifted, the hexes have pointy tops like the ones of `HexWorld`.
+    Y,
+}
+
+/// Whether the odd or the even rows or columns are shifted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StaggerIndex {
+    Odd,
+    Even,
+}
+
+/// Hex of the Tiled cell in column `x` and row `y`.
+///
+/// Maps staggered along y keep their layout, `Offset` is the same as odd staggered rows. Maps
+/// staggered along x have flat topped hexes, so they are turned a quarter to the left: their
+/// columns become rows, running from the top down, and the top row ends up on the right.
+pub fn tiled_to_axial(x: i32, y: i32, axis: StaggerAxis, index: StaggerIndex) -> Axial {
+    match axis {
+        StaggerAxis::Y => {
+            let shift = match index {
+                StaggerIndex::Odd => (y - (y & 1)) / 2,
+                StaggerIndex::Even => (y + (y & 1)) / 2,
+            };
+            Axial::new(x - shift, y)
+        }
+        StaggerAxis::X => {
+            // Shifted columns move down, after the turn the shifted rows move to the left.
+            let shift = match index {
+                StaggerIndex::Odd => (x + (x & 1)) / 2,
+                StaggerIndex::Even => (x - (x & 1)) / 2,
+            };
+            Axial::new(-y - shift, x)
+        }
+    }
+}
+
+/// Tile layer of a Tiled map.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TiledLayer {
+    pub name: String,
+    /// Column, row and global tile id of every cell that has a tile, without the flip flags.
+    pub cells: Vec<(i32, i32, u32)>,
+}
+
+/// Which layers of a Tiled map become which parts of `TileData`. Tile ids are the global ids
+/// minus one, so the first tile of the first tileset is 0.
+///
+/// Every cell of the kind layer becomes a tile. The ids of the other layers are stored in the
+/// custom fields of the tile under the layer name, except for the elevation layer.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TiledImportSettings {
+    /// Layer whose tile ids are the tile kinds, the first tile layer if `None`.
+    pub kind_layer: Option<String>,
+    /// Layer whose tile ids times `elevation_step` are the tile elevations.
+    pub elevation_layer: Option<String>,
+    pub elevation_step: f32,
+}
+
+impl Default for TiledImportSettings {
+    fn default() -> Self {
+        Self {
+            kind_layer: None,
+            elevation_layer: None,
+            elevation_step: 0.25,
+        }
+    }
+}
+
+/// Tile layers of a hexagonal map made with the Tiled editor.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TiledMap {
+    pub stagger_axis: StaggerAxis,
+    pub stagger_index: StaggerIndex,
+    /// Tile layers in file order, including the ones in groups.
+    pub layers: Vec<TiledLayer>,
+}
+
+impl TiledMap {
+    /// Reads `.tmj` and `.json` files as JSON maps, everything else as TMX.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, TiledError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("tmj" | "json") => Self::from_tmj(&text),
+            _ => Self::from_tmx(&text),
+        }
+    }
+
+    pub fn from_tmx(text: &str) -> Result<Self, TiledError> {
+        let document = roxmltree::Document::parse(text)?;
+        let map = document.root_element();
+        if !map.has_tag_name("map") {
+            return Err(TiledError::Invalid("map element"));
+        }
+
+        let (stagger_axis, stagger_index) = stagger(
+            map.attribute("orientation"),
+            map.attribute("staggeraxis"),
+            map.attribute("staggerindex"),
+        )?;
+
+        let mut layers = Vec::new();
+        tmx_layers(map, &mut layers)?;
+
+        Ok(Self {
+            stagger_axis,
+            stagger_index,
+            layers,
+        })
+    }
+
+    pub fn from_tmj(text: &str) -> Result<Self, TiledError> {
+        let map: Value = serde_json::from_str(text)?;
+
+        let (stagger_axis, stagger_index) = stagger(
+            map.get("orientation").and_then(Value::as_str),
+            map.get("staggeraxis").and_then(Value::as_str),
+            map.get("staggerindex").and_then(Value::as_str),
+        )?;
+
+        let mut layers = Vec::new();
+        tmj_layers(&map, &mut layers)?;
+
+        Ok(Self {
+            stagger_axis,
+            stagger_index,
+            layers,
+        })
+    }
+
+    pub fn layer(&self, name: &str) -> Result<&TiledLayer, TiledError> {
+        self.layers
+            .iter()
+            .find(|layer| layer.name == name)
+            .ok_or_else(|| TiledError::MissingLayer(name.into()))
+    }
+
+    pub fn tiles(
+        &self,
+        settings: &TiledImportSettings,
+    ) -> Result<Vec<(Cube, TileData)>, TiledError> {
+        let kind_layer = match &settings.kind_layer {
+            Some(name) => self.layer(name)?,
+            None => self
+                .layers
+                .first()
+                .ok_or(TiledError::Invalid("tile layer"))?,
+        };
+
+        let mut tiles: HashMap<_, _> = kind_layer
+            .cells
+            .iter()
+            .map(|(x, y, gid)| {
+                let tile = TileData {
+                    kind: gid - 1,
+                    ..TileData::default()
+                };
+                ((*x, *y), tile)
+            })
+            .collect();
+
+        if let Some(name) = &settings.elevation_layer {
+            for (x, y, gid) in &self.layer(name)?.cells {
+                if let Some(tile) = tiles.get_mut(&(*x, *y)) {
+                    tile.elevation = (gid - 1) as f32 * settings.elevation_step;
+                }
+            }
+        }
+
+        let other_layers = self.layers.iter().filter(|layer| {
+            layer.name != kind_layer.name && Some(&layer.name) != settings.elevation_layer.as_ref()
+        });
+        for layer in other_layers {
+            for (x, y, gid) in &layer.cells {
+                if let Some(tile) = tiles.get_mut(&(*x, *y)) {
+                    tile.custom
+                        .insert(layer.name.clone(), (gid - 1).to_string());
+                }
+            }
+        }
+
+        Ok(tiles
+            .into_iter()
+            .map(|((x, y), tile)| {
+                let axial = tiled_to_axial(x, y, self.stagger_axis, self.stagger_index);
+                (Cube::from(axial), tile)
+            })
+            .collect())
+    }
+}
+
+pub fn load_tiled(
+    path: impl AsRef<Path>,
+    settings: &TiledImportSettings,
+) -> Result<Vec<(Cube, TileData)>, TiledError> {
+    TiledMap::load(path)?.tiles(settings)
+}
+
+fn stagger(
+    orientation: Option<&str>,
+    axis: Option<&str>,
+    index: Option<&str>,
+) -> Result<(StaggerAxis, StaggerIndex), TiledError> {
+    if orientation != Some("hexagonal") {
+        return Err(TiledError::NotHexagonal(
+            orientation.unwrap_or("no orientation").into(),
+        ));
+    }
+
+    let axis = match axis {
+        Some("x") => StaggerAxis::X,
+        Some("y") => StaggerAxis::Y,
+        _ => return Err(TiledError::Invalid("stagger axis")),
+    };
+    let index = match index {
+        Some("odd") => StaggerIndex::Odd,
+        Some("even") => StaggerIndex::Even,
+        _ => return Err(TiledError::Invalid("stagger index")),
+    };
+
+    Ok((axis, index))
+}
+
+/// Adds the cells of a row major block of global tile ids, starting at `x` and `y`.
+fn push_cells(
+    gids: &[u32],
+    (x, y): (i32, i32),
+    width: i32,
+    cells: &mut Vec<(i32, i32, u32)>,
+) -> Result<(), TiledError> {
+    if width <= 0 {
+        return Err(TiledError::Invalid("layer width"));
+    }
+
+    for (i, gid) in gids.iter().enumerate() {
+        let gid = gid & !GID_FLAGS;
+        if gid != 0 {
+            let i = i as i32;
+            cells.push((x + i % width, y + i / width, gid));
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_csv(text: &str) -> Result<Vec<u32>, TiledError> {
+    text.split(',')
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(|value| value.parse().map_err(|_| TiledError::Invalid("tile id")))
+        .collect()
+}
+
+fn decode_base64(text: &str) -> Result<Vec<u8>, TiledError> {
+    let mut bytes = Vec::with_capacity(text.len() * 3 / 4);
+    let (mut buffer, mut bits) = (0u32, 0u32);
+
+    for c in text.bytes().filter(|c| !c.is_ascii_whitespace()) {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' => break,
+            _ => return Err(TiledError::Invalid("base64 tile data")),
+        };
+
+        buffer = (buffer << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+
+    Ok(bytes)
+}
+
+/// Global tile ids of base64 encoded layer data, stored as little endian u32.
+fn decode_gids(text: &str, compression: Option<&str>) -> Result<Vec<u32>, TiledError> {
+    if let Some(compression) = compression.filter(|compression| !compression.is_empty()) {
+        return Err(TiledError::UnsupportedEncoding(format!(
+            "base64 with {} compression",
+            compression
+        )));
+    }
+
+    let bytes = decode_base64(text)?;
+    if bytes.len() % 4 != 0 {
+        return Err(TiledError::Invalid("base64 tile data"));
+    }
+
+    Ok(bytes
+        .chunks_exact(4)
+        .map(|gid| u32::from_le_bytes(gid.try_into().unwrap()))
+        .collect())
+}
+
+fn xml_attribute<T: FromStr>(node: roxmltree::Node, name: &'static str) -> Result<T, TiledError> {
+    node.attribute(name)
+        .and_then(|value| value.parse().ok())
+        .ok_or(TiledError::Invalid(name))
+}
+
+fn tmx_layers(parent: roxmltree::Node, layers: &mut Vec<TiledLayer>) -> Result<(), TiledError> {
+    for node in parent.children().filter(roxmltree::Node::is_element) {
+        match node.tag_name().name() {
+            "layer" => {
+                let data = node
+                    .children()
+                    .find(|child| child.has_tag_name("data"))
+                    .ok_or(TiledError::Invalid("layer data"))?;
+
+                let mut cells = Vec::new();
+                // Infinite maps split their layers into chunks.
+                let chunks: Vec<_> = data
+                    .children()
+                    .filter(|child| child.has_tag_name("chunk"))
+                    .collect();
+                if chunks.is_empty() {
+                    let gids = tmx_gids(data, data)?;
+                    push_cells(&gids, (0, 0), xml_attribute(node, "width")?, &mut cells)?;
+                }
+                for chunk in chunks {
+                    let gids = tmx_gids(data, chunk)?;
+                    let origin = (xml_attribute(chunk, "x")?, xml_attribute(chunk, "y")?);
+                    push_cells(&gids, origin, xml_attribute(chunk, "width")?, &mut cells)?;
+                }
+
+                layers.push(TiledLayer {
+                    name: node.attribute("name").unwrap_or_default().into(),
+                    cells,
+                });
+            }
+            "group" => tmx_layers(node, layers)?,
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Global tile ids in `content`, which is the `data` element or one of its chunks.
+fn tmx_gids(data: roxmltree::Node, content: roxmltree::Node) -> Result<Vec<u32>, TiledError> {
+    let text = content
+        .children()
+        .filter(roxmltree::Node::is_text)
+        .filter_map(|child| child.text())
+        .collect::<String>();
+
+    match data.attribute("encoding") {
+        Some("csv") => parse_csv(&text),
+        Some("base64") => decode_gids(&text, data.attribute("compression")),
+        None => content
+            .children()
+            .filter(|child| child.has_tag_name("tile"))
+            .map(|tile| match tile.attribute("gid") {
+                Some(gid) => gid.parse().map_err(|_| TiledError::Invalid("tile id")),
+                None => Ok(0),
+            })
+            .collect(),
+        Some(encoding) => Err(TiledError::UnsupportedEncoding(encoding.into())),
+    }
+}
+
+fn json_i32(value: &Value, name: &'static str) -> Result<i32, TiledError> {
+    value
+        .get(name)
+        .and_then(Value::as_i64)
+        .map(|value| value as i32)
+        .ok_or(TiledError::Invalid(name))
+}
+
+fn tmj_layers(parent: &Value, layers: &mut Vec<TiledLayer>) -> Result<(), TiledError> {
+    let children = parent
+        .get("layers")
+        .and_then(Value::as_array)
+        .ok_or(TiledError::Invalid("layers"))?;
+
+    for layer in children {
+        match layer.get("type").and_then(Value::as_str) {
+            Some("tilelayer") => {
+                let encoding = layer.get("encoding").and_then(Value::as_str);
+                let compression = layer.get("compression").and_then(Value::as_str);
+
+                let mut cells = Vec::new();
+                match layer.get("chunks").and_then(Value::as_array) {
+                    // Infinite maps split their layers into chunks.
+                    Some(chunks) => {
+                        for chunk in chunks {
+                            let gids = tmj_gids(chunk, encoding, compression)?;
+                            let origin = (json_i32(chunk, "x")?, json_i32(chunk, "y")?);
+                            push_cells(&gids, origin, json_i32(chunk, "width")?, &mut cells)?;
+                        }
+                    }
+                    None => {
+                        let gids = tmj_gids(layer, encoding, compression)?;
+                        push_cells(&gids, (0, 0), json_i32(layer, "width")?, &mut cells)?;
+                    }
+                }
+
+                layers.push(TiledLayer {
+                    name: layer
+                        .get("name")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .into(),
+                    cells,
+                });
+            }
+            Some("group") => tmj_layers(layer, layers)?,
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Global tile ids in the `data` of a layer or chunk.
+fn tmj_gids(
+    content: &Value,
+    encoding: Option<&str>,
+    compression: Option<&str>,
+) -> Result<Vec<u32>, TiledError> {
+    match (encoding, content.get("data")) {
+        (None | Some("csv"), Some(Value::Array(gids))) => gids
+            .iter()
+            .map(|gid| {
+                gid.as_u64()
+                    .map(|gid| gid as u32)
+                    .ok_or(TiledError::Invalid("tile id"))
+            })
+            .collect(),
+        (Some("base64"), Some(Value::String(text))) => decode_gids(text, compression),
+        (Some(encoding), _) if encoding != "csv" && encoding != "base64" => {
+            Err(TiledError::UnsupportedEncoding(encoding.into()))
+        }
+        _ => Err(TiledError::Invalid("layer data")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::{Vec2, Vec3};
+
+    use super::*;
+
+    const TMX: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.10" orientation="hexagonal" renderorder="right-down" width="3" height="2" tilewidth="14" tileheight="12" infinite="0" hexsidelength="6" staggeraxis="y" staggerindex="odd">
+ <tileset firstgid="1" source="hexes.tsx"/>
+ <layer id="1" name="Ground" width="3" height="2">
+  <data encoding="csv">
+1,2,0,
+3,2147483649,1
+</data>
+ </layer>
+ <group id="3" name="Details">
+  <layer id="2" name="Height" width="3" height="2">
+   <data encoding="csv">
+3,0,0,
+0,0,2
+</data>
+  </layer>
+ </group>
+</map>"#;
+
+    const TMJ: &str = r#"{
+        "orientation": "hexagonal",
+        "staggeraxis": "x",
+        "staggerindex": "even",
+        "infinite": true,
+        "layers": [
+            {
+                "type": "tilelayer",
+                "name": "Ground",
+                "encoding": "base64",
+                "chunks": [
+                    { "x": -2, "y": 0, "width": 2, "height": 2, "data": "AQAAAAAAAAACAAAAAQAAAA==" }
+                ]
+            },
+            { "type": "objectgroup", "name": "Spawns", "objects": [] }
+        ]
+    }"#;
+
+    fn distance(a: Axial, b: Axial) -> u32 {
+        Cube::from(a).distance_to(Cube::from(b))
+    }
+
+    #[test]
+    fn staggered_neighbours_are_hex_neighbours() {
+        let hex = |x, y, axis, index| tiled_to_axial(x, y, axis, index);
+        use StaggerAxis::*;
+        use StaggerIndex::*;
+
+        // The shifted cell sits between the two cells next to it in the row or column before.
+        assert_eq!(distance(hex(0, 1, Y, Odd), hex(1, 0, Y, Odd)), 1);
+        assert_eq!(distance(hex(0, 0, Y, Even), hex(1, 1, Y, Even)), 1);
+        assert_eq!(distance(hex(0, 1, Y, Even), hex(1, 0, Y, Even)), 2);
+        assert_eq!(distance(hex(1, 0, X, Odd), hex(0, 1, X, Odd)), 1);
+        assert_eq!(distance(hex(0, 0, X, Even), hex(1, 1, X, Even)), 1);
+        assert_eq!(distance(hex(0, 1, X, Even), hex(1, 0, X, Even)), 2);
+
+        for (axis, index) in [(X, Odd), (X, Even), (Y, Odd), (Y, Even)] {
+            assert_eq!(distance(hex(3, 2, axis, index), hex(4, 2, axis, index)), 1);
+            assert_eq!(distance(hex(3, 2, axis, index), hex(3, 3, axis, index)), 1);
+        }
+    }
+
+    /// Centre of a Tiled cell on screen, with y pointing down and hexes of size 1.
+    fn tiled_position(x: i32, y: i32, axis: StaggerAxis, index: StaggerIndex) -> Vec2 {
+        let shifted = |n: i32| match index {
+            StaggerIndex::Odd => n & 1 == 1,
+            StaggerIndex::Even => n & 1 == 0,
+        };
+        let half = |n: i32| if shifted(n) { 0.5 } else { 0.0 };
+        let sqrt_3 = 3f32.sqrt();
+
+        match axis {
+            StaggerAxis::X => Vec2::new(1.5 * x as f32, sqrt_3 * (y as f32 + half(x))),
+            StaggerAxis::Y => Vec2::new(sqrt_3 * (x as f32 + half(y)), 1.5 * y as f32),
+        }
+    }
+
+    #[test]
+    fn staggered_maps_keep_their_layout() {
+        use StaggerAxis::*;
+        use StaggerIndex::*;
+
+        for (axis, index) in [(X, Odd), (X, Even), (Y, Odd), (Y, Even)] {
+            let world = |x, y| {
+                let position = Vec3::from(tiled_to_axial(x, y, axis, index));
+                Vec2::new(position.x, position.z)
+            };
+            let screen = |x, y| match axis {
+                // A quarter turn to the left, the screen y axis matches the world z axis.
+                X => tiled_position(x, y, axis, index).perp(),
+                Y => tiled_position(x, y, axis, index),
+            };
+
+            // An L shape, which would come out mirrored if the map was flipped.
+            let scale =
+                (world(0, 2) - world(0, 0)).length() / (screen(0, 2) - screen(0, 0)).length();
+            for (x, y) in [(0, 0), (1, 0), (3, 0), (0, 1), (0, 3), (2, 3), (3, 1)] {
+                let expected = scale * (screen(x, y) - screen(0, 0));
+                assert!(
+                    (world(x, y) - world(0, 0)).abs_diff_eq(expected, 1e-4),
+                    "{:?} {:?} at {}, {}",
+                    axis,
+                    index,
+                    x,
+                    y
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn odd_rows_match_offset_coordinates() {
+        let axial = tiled_to_axial(3, 5, StaggerAxis::Y, StaggerIndex::Odd);
+
+        assert_eq!(axial, Axial::from(crate::coordinates::Offset::new(3, 5)));
+    }
+
+    #[test]
+    fn tmx_layers_become_tile_data() {
+        let map = TiledMap::from_tmx(TMX).unwrap();
+        assert_eq!(map.layers.len(), 2);
+        assert_eq!(map.layers[0].cells[3], (1, 1, 1));
+
+        let settings = TiledImportSettings {
+            elevation_layer: Some("Height".into()),
+            elevation_step: 0.5,
+            ..TiledImportSettings::default()
+        };
+        let tiles = map.tiles(&settings).unwrap();
+        let tile = |x, y| {
+            let cube = Cube::from(tiled_to_axial(x, y, StaggerAxis::Y, StaggerIndex::Odd));
+            tiles.iter().find(|(c, _)| *c == cube).map(|(_, tile)| tile)
+        };
+
+        assert_eq!(tiles.len(), 5);
+        assert_eq!(tile(1, 0).unwrap().kind, 1);
+        assert_eq!(tile(0, 0).unwrap().elevation, 1.0);
+        assert_eq!(tile(2, 1).unwrap().elevation, 0.5);
+        assert!(tile(2, 0).is_none());
+
+        let tiles = map.tiles(&TiledImportSettings::default()).unwrap();
+        let custom = tiles
+            .iter()
+            .filter_map(|(_, tile)| tile.custom.get("Height"))
+            .count();
+        assert_eq!(custom, 2);
+    }
+
+    #[test]
+    fn tmj_chunks_are_placed_at_their_origin() {
+        let map = TiledMap::from_tmj(TMJ).unwrap();
+
+        assert_eq!(map.stagger_axis, StaggerAxis::X);
+        assert_eq!(map.stagger_index, StaggerIndex::Even);
+        assert_eq!(map.layers.len(), 1);
+        assert_eq!(
+            map.layers[0].cells,
+            vec![(-2, 0, 1), (-2, 1, 2), (-1, 1, 1)]
+        );
+    }
+
+    #[test]
+    fn invalid_maps_are_rejected() {
+        let orthogonal = TMX.replace("hexagonal", "orthogonal");
+        assert!(matches!(
+            TiledMap::from_tmx(&orthogonal),
+            Err(TiledError::NotHexagonal(_))
+        ));
+
+        let compressed = TMX.replace(
+            r#"encoding="csv""#,
+            r#"encoding="base64" compression="zlib""#,
+        );
+        assert!(matches!(
+            TiledMap::from_tmx(&compressed),
+            Err(TiledError::UnsupportedEncoding(_))
+        ));
+
+        let map = TiledMap::from_tmx(TMX).unwrap();
+        let settings = TiledImportSettings {
+            kind_layer: Some("Water".into()),
+            ..TiledImportSettings::default()
+        };
+        assert!(matches!(
+            map.tiles(&settings),
+            Err(TiledError::MissingLayer(_))
+        ));
+    }
+
+    #[test]
+    fn base64_tile_ids_are_little_endian() {
+        assert_eq!(
+            decode_gids("BQAAAAMAAEA=", None).unwrap(),
+            vec![5, 0x4000_0003]
+        );
+    }
+}
@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+#[cfg(feature = "physics")]
+use bevy_rapier3d::prelude::*;
+
+use crate::mesh_generation::hex::create_beveled_hex_prism;
+
+use super::hex_world::HexTileBundle;
+use super::index::HexEntityIndex;
+use super::lod::{chunk_key, ChunkMember, HexChunks};
+use super::map::{HexMap, TileData};
+use super::selection::HexSelection;
+use super::settings::HexWorldSettings;
+
+/// Lowest prism height, so tiles lowered below the ground stay visible as flat plates.
+const MIN_HEIGHT: f32 = 0.01;
+
+/// Materials for every tile kind and for selected tiles.
+pub struct TileMaterials {
+    pub kinds: Vec<Handle<StandardMaterial>>,
+    pub selected: Handle<StandardMaterial>,
+}
+
+impl TileMaterials {
+    /// Material of a tile kind. Kinds without their own material use the first one.
+    pub fn for_kind(&self, kind: u32) -> Handle<StandardMaterial> {
+        self.kinds
+            .get(kind as usize)
+            .or_else(|| self.kinds.first())
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+/// Height of the tile top above its translation.
+pub(super) fn tile_top(settings: &HexWorldSettings, tile: &TileData) -> f32 {
+    (settings.tile_height + tile.elevation).max(MIN_HEIGHT)
+}
+
+/// Spawns, updates and despawns tile entities for the hexes that changed in `HexMap`.
+///
+/// Tiles with the same height share a mesh, which is kept around for later edits.
+#[allow(clippy::too_many_arguments)]
+pub(super) fn sync_tiles(
+    mut commands: Commands,
+    mut map: ResMut<HexMap>,
+    mut chunks: ResMut<HexChunks>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut tile_meshes: Local<HashMap<i32, Handle<Mesh>>>,
+    #[cfg(feature = "physics")] mut tile_colliders: Local<HashMap<i32, Collider>>,
+    index: Res<HexEntityIndex>,
+    materials: Res<TileMaterials>,
+    selection: Res<HexSelection>,
+    settings: Res<HexWorldSettings>,
+) {
+    for cube in map.take_changes() {
+        chunks.mark_dirty(cube);
+
+        let entity = index.get(cube);
+        let tile = match map.get(cube) {
+            Some(tile) => tile,
+            None => {
+                if let Some(entity) = entity {
+                    commands.entity(entity).despawn_recursive();
+                }
+                continue;
+            }
+        };
+
+        let height = tile_top(&settings, tile);
+        // Heights are only told apart to a millimetre, to keep the number of meshes down.
+        let height_key = (height * 1000.0).round() as i32;
+        let mesh = tile_meshes
+            .entry(height_key)
+            .or_insert_with(|| {
+                meshes.add(
+                    create_beveled_hex_prism(
+                        settings.tile_size,
+                        height,
+                        settings.bevel_width,
+                        settings.bevel_segments,
+                    )
+                    .into(),
+                )
+            })
+            .clone();
+
+        let material = if selection.contains(cube) {
+            materials.selected.clone()
+        } else {
+            materials.for_kind(tile.kind)
+        };
+
+        #[cfg_attr(not(feature = "physics"), allow(unused_variables))]
+        let entity = match entity {
+            Some(entity) => {
+                commands
+                    .entity(entity)
+                    .insert(mesh.clone())
+                    .insert(material);
+                entity
+            }
+            None => commands
+                .spawn_bundle(HexTileBundle::new(cube, mesh.clone(), material))
+                .insert(ChunkMember(chunk_key(cube)))
+                .id(),
+        };
+
+        #[cfg(feature = "physics")]
+        if settings.tile_colliders {
+            let collider = tile_colliders
+                .entry(height_key)
+                .or_insert_with(|| {
+                    Collider::from_bevy_mesh(
+                        meshes.get(&mesh).expect("tile meshes are added above"),
+                        &ComputedColliderShape::TriMesh,
+                    )
+                    .expect("tile meshes are indexed triangle lists")
+                })
+                .clone();
+
+            commands
+                .entity(entity)
+                .insert(RigidBody::KinematicPositionBased)
+                .insert(collider);
+        }
+    }
+}
@@ -1 +1,2 @@
 pub mod camera_control_plugin;
+pub mod cursor_ray;
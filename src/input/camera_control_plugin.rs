@@ -1,5 +1,7 @@
 use bevy::{input::mouse::MouseMotion, prelude::*};
 
+use super::cursor_ray::{update_cursor_ray, CursorRay, CursorWindow};
+
 #[derive(Component)]
 pub struct CurrentCameraTag;
 
@@ -27,9 +29,12 @@ impl Plugin for CameraControlPlugin {
             app.insert_resource::<CameraControlSettings>(Default::default());
         }
 
-        app.add_system(move_camera)
+        app.init_resource::<CursorRay>()
+            .init_resource::<CursorWindow>()
+            .add_system(move_camera)
             .add_system(rotate_camera)
             .add_system(cursor_grab_system)
+            .add_system(update_cursor_ray.after(cursor_grab_system))
             .add_startup_system(create_camera);
     }
 }
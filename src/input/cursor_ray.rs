@@ -0,0 +1,133 @@
+use bevy::{prelude::*, render::camera::RenderTarget, window::WindowId};
+
+use super::camera_control_plugin::CurrentCameraTag;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PickingRay {
+    pub origin: Vec3,
+    pub direction: Vec3,
+}
+
+/// World space ray under the mouse cursor, `None` if the cursor is not over any camera viewport.
+/// While the cursor is grabbed, the ray goes through the centre of the current camera instead.
+#[derive(Default)]
+pub struct CursorRay(pub Option<PickingRay>);
+
+/// Window the `CursorRay` comes from, `None` if there is no ray.
+#[derive(Default)]
+pub struct CursorWindow(pub Option<WindowId>);
+
+/// Normalized device coordinates of a cursor position, `None` if it lies outside the viewport.
+///
+/// `cursor` is in logical pixels from the bottom left of the window, as reported by `Window`,
+/// while `viewport` is given as position and size in logical pixels from the top left.
+fn cursor_to_ndc(cursor: Vec2, window_size: Vec2, viewport: Option<(Vec2, Vec2)>) -> Option<Vec2> {
+    let (position, size) = viewport.unwrap_or((Vec2::ZERO, window_size));
+    let from_top_left = Vec2::new(cursor.x, window_size.y - cursor.y);
+    let relative = (from_top_left - position) / size;
+
+    if relative.cmplt(Vec2::ZERO).any() || relative.cmpgt(Vec2::ONE).any() {
+        return None;
+    }
+
+    Some(Vec2::new(2.0 * relative.x - 1.0, 1.0 - 2.0 * relative.y))
+}
+
+fn camera_ray(camera: &Camera, transform: &GlobalTransform, ndc: Vec2) -> Option<PickingRay> {
+    // Depth is reversed, 1 is the near plane and the infinite far plane lies at 0.
+    let near = camera.ndc_to_world(transform, ndc.extend(1.0))?;
+    let far = camera.ndc_to_world(transform, ndc.extend(0.5))?;
+
+    Some(PickingRay {
+        origin: near,
+        direction: (far - near).try_normalize()?,
+    })
+}
+
+pub fn update_cursor_ray(
+    windows: Res<Windows>,
+    camera_query: Query<(&Camera, &GlobalTransform, Option<&CurrentCameraTag>)>,
+    mut cursor_ray: ResMut<CursorRay>,
+    mut cursor_window: ResMut<CursorWindow>,
+) {
+    let hit = camera_query
+        .iter()
+        .filter(|(camera, _, _)| camera.is_active)
+        .filter_map(|(camera, transform, current)| {
+            let (id, window) = match camera.target {
+                RenderTarget::Window(id) => (id, windows.get(id)?),
+                RenderTarget::Image(_) => return None,
+            };
+
+            let ndc = if window.cursor_locked() {
+                // Only the camera that is being steered picks through the crosshair.
+                if current.is_none() {
+                    return None;
+                }
+                Vec2::ZERO
+            } else {
+                let scale = window.scale_factor() as f32;
+                let viewport = camera.viewport.as_ref().map(|viewport| {
+                    (
+                        viewport.physical_position.as_vec2() / scale,
+                        viewport.physical_size.as_vec2() / scale,
+                    )
+                });
+                let window_size = Vec2::new(window.width(), window.height());
+
+                cursor_to_ndc(window.cursor_position()?, window_size, viewport)?
+            };
+
+            Some((camera.priority, camera_ray(camera, transform, ndc)?, id))
+        })
+        // Overlapping viewports are drawn in priority order, so the highest one is on top.
+        .max_by_key(|(priority, _, _)| *priority);
+
+    let (ray, window) = match hit {
+        Some((_, ray, window)) => (Some(ray), Some(window)),
+        None => (None, None),
+    };
+    if cursor_ray.0 != ray {
+        cursor_ray.0 = ray;
+    }
+    if cursor_window.0 != window {
+        cursor_window.0 = window;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn window_centre_is_ndc_origin() {
+        let ndc = cursor_to_ndc(Vec2::new(400.0, 300.0), Vec2::new(800.0, 600.0), None);
+
+        assert_eq!(ndc, Some(Vec2::ZERO));
+    }
+
+    #[test]
+    fn window_corners_map_to_ndc_corners() {
+        let size = Vec2::new(800.0, 600.0);
+
+        assert_eq!(
+            cursor_to_ndc(Vec2::ZERO, size, None),
+            Some(Vec2::new(-1.0, -1.0))
+        );
+        assert_eq!(cursor_to_ndc(size, size, None), Some(Vec2::new(1.0, 1.0)));
+    }
+
+    #[test]
+    fn viewport_is_measured_from_the_top_left() {
+        let size = Vec2::new(800.0, 600.0);
+        // Right half of the top half of the window.
+        let viewport = Some((Vec2::new(400.0, 0.0), Vec2::new(400.0, 300.0)));
+
+        assert_eq!(
+            cursor_to_ndc(Vec2::new(600.0, 450.0), size, viewport),
+            Some(Vec2::ZERO)
+        );
+        assert_eq!(cursor_to_ndc(Vec2::new(200.0, 450.0), size, viewport), None);
+        assert_eq!(cursor_to_ndc(Vec2::new(600.0, 150.0), size, viewport), None);
+    }
+}
@@ -3,8 +3,9 @@ mod hex_world;
 mod input;
 mod mesh_generation;
 
+#[cfg(feature = "physics")]
 use bevy_rapier3d::prelude::*;
-use hex_world::HexWorld;
+use hex_world::{HexTooltipSettings, HexWorld};
 use input::camera_control_plugin::CameraControlPlugin;
 
 use bevy::{prelude::*, render::texture::ImageSettings};
@@ -16,16 +17,24 @@ fn main() {
         ..Default::default()
     };
 
-    App::new()
-        .insert_resource(window_descriptor)
+    let mut app = App::new();
+
+    app.insert_resource(window_descriptor)
         .insert_resource(ImageSettings::default_nearest())
         .add_plugins(DefaultPlugins)
-        .add_plugin(CameraControlPlugin)
-        .add_plugin(RapierPhysicsPlugin::<NoUserData>::default())
-        .add_startup_system(create_plane_and_light)
-        // .add_plugin(RapierDebugRenderPlugin::default())
-        .add_plugin(HexWorld)
-        .run()
+        .add_plugin(CameraControlPlugin);
+
+    #[cfg(feature = "physics")]
+    app.add_plugin(RapierPhysicsPlugin::<NoUserData>::default());
+    // .add_plugin(RapierDebugRenderPlugin::default())
+
+    app.insert_resource(HexTooltipSettings {
+        font: Some("fonts/DejaVuSansMono.ttf".into()),
+        ..default()
+    })
+    .add_startup_system(create_plane_and_light)
+    .add_plugin(HexWorld)
+    .run()
 }
 
 fn create_plane_and_light(
@@ -1,2 +1,8 @@
+pub mod export;
 pub mod hex;
+pub mod import;
+pub mod intersection;
+pub mod lod;
+pub mod outline;
 pub mod submesh;
+pub mod terrain;
@@ -0,0 +1,482 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use bevy::prelude::Vec3;
+use serde_json::json;
+
+use super::submesh::SubMesh;
+
+fn face_normal(mesh: &SubMesh, indices: [u32; 3]) -> Vec3 {
+    let [a, b, c] = indices.map(|i| mesh.vertices()[i as usize].position);
+    (b - a).cross(c - a).normalize_or_zero()
+}
+
+/// Writes the mesh as Wavefront OBJ with positions, normals and texture coordinates. OBJ puts the
+/// texture origin in the bottom left corner, so the v coordinate is flipped.
+pub fn write_obj<W: Write>(mesh: &SubMesh, writer: &mut W) -> io::Result<()> {
+    writeln!(writer, "o hex")?;
+
+    for vertex in mesh.vertices() {
+        let p = vertex.position;
+        writeln!(writer, "v {} {} {}", p.x, p.y, p.z)?;
+    }
+    for vertex in mesh.vertices() {
+        writeln!(writer, "vt {} {}", vertex.uv.x, 1.0 - vertex.uv.y)?;
+    }
+    for vertex in mesh.vertices() {
+        let n = vertex.normal;
+        writeln!(writer, "vn {} {} {}", n.x, n.y, n.z)?;
+    }
+
+    for triangle in mesh.triangles() {
+        let [a, b, c] = triangle.indices.map(|i| i + 1);
+        writeln!(writer, "f {a}/{a}/{a} {b}/{b}/{b} {c}/{c}/{c}")?;
+    }
+
+    Ok(())
+}
+
+/// Writes the mesh as binary STL. STL has no shared vertices, normals are per face.
+pub fn write_stl<W: Write>(mesh: &SubMesh, writer: &mut W) -> io::Result<()> {
+    let mut header = [0u8; 80];
+    let name = b"bevy_hexes";
+    header[..name.len()].copy_from_slice(name);
+    writer.write_all(&header)?;
+    writer.write_all(&(mesh.triangles().len() as u32).to_le_bytes())?;
+
+    for triangle in mesh.triangles() {
+        let normal = face_normal(mesh, triangle.indices);
+        let corners = triangle
+            .indices
+            .map(|i| mesh.vertices()[i as usize].position);
+
+        for v in std::iter::once(normal).chain(corners) {
+            for component in v.to_array() {
+                writer.write_all(&component.to_le_bytes())?;
+            }
+        }
+        writer.write_all(&0u16.to_le_bytes())?;
+    }
+
+    Ok(())
+}
+
+struct GltfBuffer {
+    json: String,
+    bin: Vec<u8>,
+}
+
+fn invalid_mesh(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+/// Escapes everything but unreserved characters and path separators, so any file name is a valid
+/// relative URI.
+fn percent_encode(path: &str) -> String {
+    path.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+/// glTF does not allow empty buffers or non-finite accessor bounds, so meshes without triangles
+/// or with non-finite vertices are rejected.
+fn build_gltf(mesh: &SubMesh, bin_uri: Option<&str>) -> io::Result<GltfBuffer> {
+    if mesh.triangles().is_empty() {
+        return Err(invalid_mesh(
+            "cannot export a mesh without triangles to glTF",
+        ));
+    }
+    let finite = mesh
+        .vertices()
+        .iter()
+        .all(|v| v.position.is_finite() && v.normal.is_finite() && v.uv.is_finite());
+    if !finite {
+        return Err(invalid_mesh(
+            "cannot export a mesh with non-finite vertices to glTF",
+        ));
+    }
+
+    const ARRAY_BUFFER: u32 = 34962;
+    const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+    const FLOAT: u32 = 5126;
+    const UNSIGNED_INT: u32 = 5125;
+
+    let mut bin = Vec::new();
+    let mut views = vec![];
+    let mut push_view = |bin: &mut Vec<u8>, data: Vec<[u8; 4]>, target: u32| {
+        let offset = bin.len();
+        data.iter().for_each(|bytes| bin.extend(bytes));
+        views.push(json!({
+            "buffer": 0,
+            "byteOffset": offset,
+            "byteLength": bin.len() - offset,
+            "target": target,
+        }));
+    };
+
+    let floats = |components: Vec<f32>| components.into_iter().map(f32::to_le_bytes).collect();
+    let positions = mesh.vertices().iter().flat_map(|v| v.position.to_array());
+    let normals = mesh.vertices().iter().flat_map(|v| v.normal.to_array());
+    let uvs = mesh.vertices().iter().flat_map(|v| v.uv.to_array());
+    let indices = mesh.triangles().iter().flat_map(|t| t.indices);
+    push_view(&mut bin, floats(positions.collect()), ARRAY_BUFFER);
+    push_view(&mut bin, floats(normals.collect()), ARRAY_BUFFER);
+    push_view(&mut bin, floats(uvs.collect()), ARRAY_BUFFER);
+    push_view(
+        &mut bin,
+        indices.map(u32::to_le_bytes).collect(),
+        ELEMENT_ARRAY_BUFFER,
+    );
+
+    let (min, max) = mesh.vertices().iter().map(|v| v.position).fold(
+        (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)),
+        |(min, max), p| (min.min(p), max.max(p)),
+    );
+    let vertex_count = mesh.vertices().len();
+    let accessors = json!([
+        {
+            "bufferView": 0,
+            "componentType": FLOAT,
+            "count": vertex_count,
+            "type": "VEC3",
+            "min": min.to_array(),
+            "max": max.to_array(),
+        },
+        { "bufferView": 1, "componentType": FLOAT, "count": vertex_count, "type": "VEC3" },
+        { "bufferView": 2, "componentType": FLOAT, "count": vertex_count, "type": "VEC2" },
+        {
+            "bufferView": 3,
+            "componentType": UNSIGNED_INT,
+            "count": 3 * mesh.triangles().len(),
+            "type": "SCALAR",
+        },
+    ]);
+
+    let mut buffer = json!({ "byteLength": bin.len() });
+    if let Some(uri) = bin_uri {
+        buffer["uri"] = percent_encode(uri).into();
+    }
+
+    let document = json!({
+        "asset": { "version": "2.0", "generator": "bevy_hexes" },
+        "scene": 0,
+        "scenes": [{ "nodes": [0] }],
+        "nodes": [{ "mesh": 0 }],
+        "meshes": [{
+            "primitives": [{
+                "attributes": { "POSITION": 0, "NORMAL": 1, "TEXCOORD_0": 2 },
+                "indices": 3,
+                "mode": 4,
+            }],
+        }],
+        "accessors": accessors,
+        "bufferViews": views,
+        "buffers": [buffer],
+    });
+
+    Ok(GltfBuffer {
+        json: document.to_string(),
+        bin,
+    })
+}
+
+/// Writes the mesh as glTF 2.0 JSON to `json` and its binary buffer to `bin`. `bin_uri` is the
+/// path of the binary buffer relative to the JSON file, it is percent-encoded when written.
+pub fn write_gltf<J: Write, B: Write>(
+    mesh: &SubMesh,
+    json: &mut J,
+    bin: &mut B,
+    bin_uri: &str,
+) -> io::Result<()> {
+    let gltf = build_gltf(mesh, Some(bin_uri))?;
+    json.write_all(gltf.json.as_bytes())?;
+    bin.write_all(&gltf.bin)
+}
+
+/// Writes the mesh as a single binary glTF 2.0 (`.glb`) file.
+pub fn write_glb<W: Write>(mesh: &SubMesh, writer: &mut W) -> io::Result<()> {
+    const MAGIC: u32 = 0x46546C67;
+    const VERSION: u32 = 2;
+    const CHUNK_JSON: u32 = 0x4E4F534A;
+    const CHUNK_BIN: u32 = 0x004E4942;
+
+    let GltfBuffer { json, mut bin } = build_gltf(mesh, None)?;
+
+    // Chunks have to be aligned to four bytes, JSON is padded with spaces and BIN with zeros.
+    let mut json = json.into_bytes();
+    json.resize(json.len().next_multiple_of(4), b' ');
+    bin.resize(bin.len().next_multiple_of(4), 0);
+
+    let total_length = 12 + 8 + json.len() + 8 + bin.len();
+
+    for word in [MAGIC, VERSION, total_length as u32] {
+        writer.write_all(&word.to_le_bytes())?;
+    }
+    for (chunk_type, data) in [(CHUNK_JSON, &json), (CHUNK_BIN, &bin)] {
+        writer.write_all(&(data.len() as u32).to_le_bytes())?;
+        writer.write_all(&chunk_type.to_le_bytes())?;
+        writer.write_all(data)?;
+    }
+
+    Ok(())
+}
+
+/// Saves the mesh to `path`, picking the format from the extension (`obj`, `stl`, `gltf` or
+/// `glb`). A `.gltf` file gets its binary buffer written next to it as `.bin`. The files are
+/// encoded in memory first, so a mesh that can't be exported leaves existing files untouched.
+pub fn save(mesh: &SubMesh, path: impl AsRef<Path>) -> io::Result<()> {
+    let path = path.as_ref();
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase());
+
+    if !matches!(extension.as_deref(), Some("obj" | "stl" | "glb" | "gltf")) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("unsupported mesh export format: {}", path.display()),
+        ));
+    }
+
+    let mut bytes = vec![];
+    let mut bin = None;
+
+    match extension.as_deref() {
+        Some("obj") => write_obj(mesh, &mut bytes)?,
+        Some("stl") => write_stl(mesh, &mut bytes)?,
+        Some("glb") => write_glb(mesh, &mut bytes)?,
+        _ => {
+            let bin_path = path.with_extension("bin");
+            let bin_uri = bin_path
+                .file_name()
+                .expect("the path has a file name, it has an extension")
+                .to_string_lossy()
+                .into_owned();
+            let mut bin_bytes = vec![];
+            write_gltf(mesh, &mut bytes, &mut bin_bytes, &bin_uri)?;
+            bin = Some((bin_path, bin_bytes));
+        }
+    }
+
+    // The buffer goes first, so the JSON never points to a missing file.
+    if let Some((bin_path, bin_bytes)) = bin {
+        fs::write(bin_path, bin_bytes)?;
+    }
+    fs::write(path, bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh_generation::hex::create_hex_prism;
+
+    fn read_f32(bytes: &[u8], offset: usize) -> f32 {
+        f32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn obj_round_trips_positions_and_faces() {
+        let mesh = create_hex_prism(0.5, 0.25);
+        let mut obj = vec![];
+        write_obj(&mesh, &mut obj).unwrap();
+        let obj = String::from_utf8(obj).unwrap();
+
+        let positions: Vec<Vec3> = obj
+            .lines()
+            .filter_map(|l| l.strip_prefix("v "))
+            .map(|l| {
+                let c: Vec<f32> = l.split(' ').map(|c| c.parse().unwrap()).collect();
+                Vec3::new(c[0], c[1], c[2])
+            })
+            .collect();
+        let faces: Vec<[u32; 3]> = obj
+            .lines()
+            .filter_map(|l| l.strip_prefix("f "))
+            .map(|l| {
+                let idx: Vec<u32> = l
+                    .split(' ')
+                    .map(|c| c.split('/').next().unwrap().parse::<u32>().unwrap() - 1)
+                    .collect();
+                [idx[0], idx[1], idx[2]]
+            })
+            .collect();
+
+        let original: Vec<_> = mesh.vertices().iter().map(|v| v.position).collect();
+        let original_faces: Vec<_> = mesh.triangles().iter().map(|t| t.indices).collect();
+        assert_eq!(positions, original);
+        assert_eq!(faces, original_faces);
+        assert_eq!(
+            obj.lines().filter(|l| l.starts_with("vn ")).count(),
+            original.len()
+        );
+        assert_eq!(
+            obj.lines().filter(|l| l.starts_with("vt ")).count(),
+            original.len()
+        );
+    }
+
+    #[test]
+    fn stl_round_trips_triangles() {
+        let mesh = create_hex_prism(0.5, 0.25);
+        let mut stl = vec![];
+        write_stl(&mesh, &mut stl).unwrap();
+
+        let count = read_u32(&stl, 80) as usize;
+        assert_eq!(count, mesh.triangles().len());
+        assert_eq!(stl.len(), 84 + count * 50);
+
+        for (i, triangle) in mesh.triangles().iter().enumerate() {
+            let record = 84 + i * 50;
+            let normal = Vec3::new(
+                read_f32(&stl, record),
+                read_f32(&stl, record + 4),
+                read_f32(&stl, record + 8),
+            );
+            assert!(normal.abs_diff_eq(face_normal(&mesh, triangle.indices), 1e-6));
+
+            for (corner, index) in triangle.indices.iter().enumerate() {
+                let offset = record + 12 * (corner + 1);
+                let position = Vec3::new(
+                    read_f32(&stl, offset),
+                    read_f32(&stl, offset + 4),
+                    read_f32(&stl, offset + 8),
+                );
+                assert_eq!(position, mesh.vertices()[*index as usize].position);
+            }
+        }
+    }
+
+    #[test]
+    fn glb_has_valid_layout_and_buffer() {
+        let mesh = create_hex_prism(0.5, 0.25);
+        let mut glb = vec![];
+        write_glb(&mesh, &mut glb).unwrap();
+
+        assert_eq!(&glb[0..4], b"glTF");
+        assert_eq!(read_u32(&glb, 4), 2);
+        assert_eq!(read_u32(&glb, 8) as usize, glb.len());
+
+        let json_length = read_u32(&glb, 12) as usize;
+        assert_eq!(&glb[16..20], b"JSON");
+        assert_eq!(json_length % 4, 0);
+        let json = std::str::from_utf8(&glb[20..20 + json_length]).unwrap();
+        assert!(json.contains(r#""POSITION":0"#));
+        assert!(!json.contains("uri"));
+
+        let bin_header = 20 + json_length;
+        assert_eq!(&glb[bin_header + 4..bin_header + 8], b"BIN\0");
+        let bin = &glb[bin_header + 8..];
+
+        let vertex_count = mesh.vertices().len();
+        assert!(bin.len() >= vertex_count * 32 + mesh.triangles().len() * 12);
+        for (i, vertex) in mesh.vertices().iter().enumerate() {
+            let position = Vec3::new(
+                read_f32(bin, 12 * i),
+                read_f32(bin, 12 * i + 4),
+                read_f32(bin, 12 * i + 8),
+            );
+            assert_eq!(position, vertex.position);
+        }
+        let indices_offset = vertex_count * 32;
+        for (i, index) in mesh.triangles().iter().flat_map(|t| t.indices).enumerate() {
+            assert_eq!(read_u32(bin, indices_offset + 4 * i), index);
+        }
+    }
+
+    #[test]
+    fn gltf_json_describes_binary_buffer() {
+        let mesh = create_hex_prism(0.5, 0.25);
+        let (mut json, mut bin) = (vec![], vec![]);
+        write_gltf(&mesh, &mut json, &mut bin, "my hex \"#1\".bin").unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+
+        let buffer = &json["buffers"][0];
+        assert_eq!(buffer["uri"], "my%20hex%20%22%231%22.bin");
+        assert_eq!(buffer["byteLength"], bin.len());
+
+        let views = json["bufferViews"].as_array().unwrap();
+        let mut end = 0;
+        for view in views {
+            let offset = view["byteOffset"].as_u64().unwrap();
+            let length = view["byteLength"].as_u64().unwrap();
+            assert_eq!(offset, end);
+            assert!(length > 0);
+            end = offset + length;
+        }
+        assert_eq!(end as usize, bin.len());
+
+        let vertex_count = mesh.vertices().len();
+        let counts: Vec<_> = json["accessors"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|accessor| accessor["count"].as_u64().unwrap() as usize)
+            .collect();
+        assert_eq!(
+            counts,
+            [
+                vertex_count,
+                vertex_count,
+                vertex_count,
+                3 * mesh.triangles().len()
+            ]
+        );
+        assert_eq!(views[0]["byteLength"], 12 * vertex_count);
+        assert_eq!(views[2]["byteLength"], 8 * vertex_count);
+        assert_eq!(json["accessors"][0]["max"][1], 0.25);
+    }
+
+    #[test]
+    fn gltf_rejects_empty_and_non_finite_meshes() {
+        let empty = SubMesh::new(vec![], vec![]).unwrap();
+        let error = write_glb(&empty, &mut vec![]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+
+        let mut broken = create_hex_prism(0.5, 0.25);
+        broken.vertices[0].position.y = f32::INFINITY;
+        let error = write_glb(&broken, &mut vec![]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn failed_save_keeps_existing_files() {
+        let empty = SubMesh::new(vec![], vec![]).unwrap();
+
+        for extension in ["glb", "gltf"] {
+            let path = std::env::temp_dir().join(format!(
+                "bevy_hexes_failed_save_{}.{extension}",
+                std::process::id()
+            ));
+            let bin_path = path.with_extension("bin");
+            fs::write(&path, b"previous export").unwrap();
+
+            let error = save(&empty, &path).unwrap_err();
+            let contents = fs::read(&path).unwrap();
+            let bin_written = bin_path.exists();
+            fs::remove_file(&path).unwrap();
+
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+            assert_eq!(contents, b"previous export");
+            assert!(!bin_written);
+        }
+    }
+
+    #[test]
+    fn save_rejects_unknown_extension() {
+        let path = std::env::temp_dir().join("bevy_hexes_export_test.xyz");
+        let error = save(&create_hex_prism(0.5, 0.25), &path).unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert!(!path.exists());
+    }
+}
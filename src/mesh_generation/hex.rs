@@ -1,20 +1,34 @@
+use std::collections::HashMap;
+
 use bevy::prelude::{Quat, Vec2, Vec3};
 
-use super::submesh::{SubMesh, Triangle, Vertex};
+use super::submesh::{SubMesh, Triangle, Vertex, INDICES_IN_RANGE};
+
+/// Stops a generator before a non-finite dimension turns into a mesh full of NaN positions.
+pub(super) fn assert_finite(name: &str, value: f32) {
+    assert!(value.is_finite(), "{name} must be finite, got {value}");
+}
 
-fn get_hex_point(n: u8, size: f32) -> Vec3 {
+pub(super) fn get_hex_point(n: u8, size: f32) -> Vec3 {
     let degree = (60.0 * (n as f32)) - 30.0;
     let radians = degree.to_radians();
     size * Vec3::new(radians.cos(), 0.0, radians.sin())
 }
 
-fn get_hex_side_normal(n: u8) -> Vec3 {
+pub(super) fn get_hex_side_normal(n: u8) -> Vec3 {
     let degree = 60.0 * (n as f32);
     let radians = degree.to_radians();
     Vec3::new(radians.cos(), 0.0, radians.sin())
 }
 
+/// Flat hex facing up. Panics if `size` is not finite.
 pub fn create_hex(size: f32) -> SubMesh {
+    assert_finite("size", size);
+    create_hex_top(size, 0.0)
+}
+
+/// Flat hex facing up at `height`, built in place so no translation can fail.
+fn create_hex_top(size: f32, height: f32) -> SubMesh {
     let positions = [
         Vec3::new(0.0, 0.0, 0.0),
         get_hex_point(0, size),
@@ -27,10 +41,9 @@ pub fn create_hex(size: f32) -> SubMesh {
 
     let vertices = positions
         .into_iter()
-        .map(|pos| Vertex {
-            position: pos,
-            normal: Vec3::Y,
-            uv: Vec2::ZERO,
+        .map(|pos| {
+            let uv = Vec2::new(pos.x, pos.z) / (2.0 * size) + Vec2::splat(0.5);
+            Vertex::new(pos + height * Vec3::Y, Vec3::Y, uv)
         })
         .collect();
 
@@ -43,7 +56,7 @@ pub fn create_hex(size: f32) -> SubMesh {
         Triangle { indices: [0, 1, 6] },
     ];
 
-    SubMesh::new(vertices, triangles).unwrap()
+    SubMesh::new(vertices, triangles).expect(INDICES_IN_RANGE)
 }
 
 fn create_hex_prism_side(n1: u8, n2: u8, size: f32, height: f32) -> [Vertex; 4] {
@@ -51,31 +64,19 @@ fn create_hex_prism_side(n1: u8, n2: u8, size: f32, height: f32) -> [Vertex; 4]
     let side_n2 = get_hex_point(n2, size);
     let normal = get_hex_side_normal(n1);
 
-    let v1 = Vertex {
-        position: side_n1,
-        normal,
-        uv: Vec2::ZERO,
-    };
-    let v2 = Vertex {
-        position: side_n1 + (height * Vec3::Y),
-        normal,
-        uv: Vec2::ZERO,
-    };
-    let v3 = Vertex {
-        position: side_n2,
-        normal,
-        uv: Vec2::ZERO,
-    };
-    let v4 = Vertex {
-        position: side_n2 + (height * Vec3::Y),
-        normal,
-        uv: Vec2::ZERO,
-    };
+    let v1 = Vertex::new(side_n1, normal, Vec2::new(0.0, 1.0));
+    let v2 = Vertex::new(side_n1 + (height * Vec3::Y), normal, Vec2::new(0.0, 0.0));
+    let v3 = Vertex::new(side_n2, normal, Vec2::new(1.0, 1.0));
+    let v4 = Vertex::new(side_n2 + (height * Vec3::Y), normal, Vec2::new(1.0, 0.0));
 
     return [v1, v2, v3, v4];
 }
 
+/// Walls of a prism from the ground up to `height`. Panics if `size` or `height` is not finite.
 pub fn create_hex_prism_sides(size: f32, height: f32) -> SubMesh {
+    assert_finite("size", size);
+    assert_finite("height", height);
+
     let mut vertices = vec![];
     let mut triangles = vec![];
 
@@ -90,17 +91,340 @@ pub fn create_hex_prism_sides(size: f32, height: f32) -> SubMesh {
             .for_each(|t| triangles.push(t))
     }
 
-    return SubMesh::new(vertices, triangles).unwrap();
+    return SubMesh::new(vertices, triangles).expect(INDICES_IN_RANGE);
 }
 
+/// Closed prism from the ground up to `height`. Panics if `size` or `height` is not finite.
 pub fn create_hex_prism(size: f32, height: f32) -> SubMesh {
-    let front_hex = create_hex(size).translate(Vec3::Y * height).unwrap();
+    assert_finite("size", size);
+    assert_finite("height", height);
+
+    let front_hex = create_hex_top(size, height);
     let back_hex = create_hex(size)
         .rotate(Quat::from_rotation_x(180.0_f32.to_radians()))
-        .unwrap();
+        .expect("a constant half turn is a valid rotation");
     let double_hex = front_hex.merge(back_hex);
 
     let prism_sides = create_hex_prism_sides(size, height);
 
     double_hex.merge(prism_sides)
 }
+
+pub(super) fn get_hex_side_apothem(size: f32) -> f32 {
+    size * 30.0_f32.to_radians().cos()
+}
+
+fn create_bevel_band(
+    side: u8,
+    (upper_apothem, upper_height): (f32, f32),
+    (lower_apothem, lower_height): (f32, f32),
+    normal: Vec3,
+    (upper_v, lower_v): (f32, f32),
+) -> [Vertex; 4] {
+    let corner_factor = 1.0 / get_hex_side_apothem(1.0);
+    let upper_size = upper_apothem * corner_factor;
+    let lower_size = lower_apothem * corner_factor;
+
+    let v1 = Vertex::new(
+        get_hex_point(side, lower_size) + lower_height * Vec3::Y,
+        normal,
+        Vec2::new(0.0, lower_v),
+    );
+    let v2 = Vertex::new(
+        get_hex_point(side, upper_size) + upper_height * Vec3::Y,
+        normal,
+        Vec2::new(0.0, upper_v),
+    );
+    let v3 = Vertex::new(
+        get_hex_point(side + 1, lower_size) + lower_height * Vec3::Y,
+        normal,
+        Vec2::new(1.0, lower_v),
+    );
+    let v4 = Vertex::new(
+        get_hex_point(side + 1, upper_size) + upper_height * Vec3::Y,
+        normal,
+        Vec2::new(1.0, upper_v),
+    );
+
+    [v1, v2, v3, v4]
+}
+
+/// Ring of quads that rounds off the top edge of a prism. The profile is a quarter circle with
+/// radius `bevel_width` split into `segments` flat facets, a single segment gives a chamfer.
+/// Panics if a dimension is not finite.
+pub fn create_hex_bevel(size: f32, height: f32, bevel_width: f32, segments: u8) -> SubMesh {
+    assert_finite("size", size);
+    assert_finite("height", height);
+    assert_finite("bevel_width", bevel_width);
+
+    let segments = segments.max(1);
+    let apothem = get_hex_side_apothem(size);
+    let inset_apothem = apothem - bevel_width;
+
+    let profile = |k: u8| {
+        let angle = (k as f32 / segments as f32) * 90.0_f32.to_radians();
+        (
+            inset_apothem + bevel_width * angle.sin(),
+            height - bevel_width + bevel_width * angle.cos(),
+        )
+    };
+
+    let mut vertices = vec![];
+    let mut triangles = vec![];
+
+    let tri_indices = [[0u32, 3, 2], [0, 1, 3]];
+
+    for k in 0..segments {
+        let mid_angle = ((k as f32 + 0.5) / segments as f32) * 90.0_f32.to_radians();
+        let v_range = (k as f32 / segments as f32, (k + 1) as f32 / segments as f32);
+
+        for i in 0..6 {
+            let normal = mid_angle.cos() * Vec3::Y + mid_angle.sin() * get_hex_side_normal(i);
+            let tri_offset = vertices.len() as u32;
+            vertices.extend_from_slice(&create_bevel_band(
+                i,
+                profile(k),
+                profile(k + 1),
+                normal,
+                v_range,
+            ));
+            tri_indices
+                .iter()
+                .map(|[a, b, c]| Triangle::new(*a + tri_offset, *b + tri_offset, *c + tri_offset))
+                .for_each(|t| triangles.push(t))
+        }
+    }
+
+    SubMesh::new(vertices, triangles).expect(INDICES_IN_RANGE)
+}
+
+/// Hex prism whose top edges are beveled. `bevel_width` is clamped so the bevel fits into both the
+/// height of the prism and the top face. Panics if a dimension is not finite.
+pub fn create_beveled_hex_prism(size: f32, height: f32, bevel_width: f32, segments: u8) -> SubMesh {
+    assert_finite("size", size);
+    assert_finite("height", height);
+    assert_finite("bevel_width", bevel_width);
+
+    let apothem = get_hex_side_apothem(size);
+    let bevel_width = bevel_width.clamp(0.0, height.min(apothem));
+    let inset_size = (apothem - bevel_width) / get_hex_side_apothem(1.0);
+
+    let front_hex = create_hex_top(inset_size, height);
+    let back_hex = create_hex(size)
+        .rotate(Quat::from_rotation_x(180.0_f32.to_radians()))
+        .expect("a constant half turn is a valid rotation");
+    let bevel = create_hex_bevel(size, height, bevel_width, segments);
+    let beveled_hex = front_hex.merge(back_hex).merge(bevel);
+
+    let side_height = height - bevel_width;
+    if side_height <= f32::EPSILON {
+        return beveled_hex;
+    }
+
+    beveled_hex.merge(create_hex_prism_sides(size, side_height))
+}
+
+/// Flat hex top split into `rings` rings of triangles on a triangular lattice. Every vertex is
+/// lifted by `displacement`, which is evaluated at the world position `origin + local position`,
+/// and its normal is derived from the slope of `displacement` at that point. As long as `size`
+/// matches the spacing of the hex layout, neighbouring tiles share their edge vertices exactly, so
+/// displaced tiles meet without cracks. Panics if `size` is not finite.
+pub fn create_subdivided_hex<F>(size: f32, rings: u32, origin: Vec3, displacement: F) -> SubMesh
+where
+    F: Fn(Vec3) -> f32,
+{
+    assert_finite("size", size);
+    let rings = rings.max(1) as i32;
+    let step = size / rings as f32;
+    let e1 = get_hex_point(0, step);
+    let e2 = get_hex_point(1, step);
+
+    let height_at = |local: Vec3| displacement(origin + local);
+    let normal_at = |local: Vec3| {
+        let delta = 0.25 * step;
+        let dx = height_at(local + delta * Vec3::X) - height_at(local - delta * Vec3::X);
+        let dz = height_at(local + delta * Vec3::Z) - height_at(local - delta * Vec3::Z);
+        Vec3::new(-dx, 2.0 * delta, -dz).normalize()
+    };
+
+    let in_hex = |a: i32, b: i32| a.abs() <= rings && b.abs() <= rings && (a + b).abs() <= rings;
+
+    let mut lattice_indices = HashMap::new();
+    let mut vertices = vec![];
+
+    for a in -rings..=rings {
+        for b in -rings..=rings {
+            if !in_hex(a, b) {
+                continue;
+            }
+
+            let local = a as f32 * e1 + b as f32 * e2;
+            let uv = Vec2::new(local.x, local.z) / (2.0 * size) + Vec2::splat(0.5);
+            let position = local + height_at(local) * Vec3::Y;
+
+            lattice_indices.insert((a, b), vertices.len() as u32);
+            vertices.push(Vertex::new(position, normal_at(local), uv));
+        }
+    }
+
+    let mut triangles = vec![];
+    let mut push_triangle = |corners: [(i32, i32); 3]| {
+        if corners.iter().all(|&(a, b)| in_hex(a, b)) {
+            let [a, b, c] = corners.map(|corner| lattice_indices[&corner]);
+            triangles.push(Triangle::new(a, b, c));
+        }
+    };
+
+    for a in -rings..rings {
+        for b in -rings..rings {
+            push_triangle([(a, b), (a, b + 1), (a + 1, b)]);
+            push_triangle([(a + 1, b), (a, b + 1), (a + 1, b + 1)]);
+        }
+    }
+
+    SubMesh::new(vertices, triangles).expect(INDICES_IN_RANGE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_faces_match_normals(mesh: &SubMesh) {
+        for triangle in mesh.triangles() {
+            let [a, b, c] = triangle.indices.map(|i| mesh.vertices()[i as usize]);
+            let face_normal = (b.position - a.position)
+                .cross(c.position - a.position)
+                .normalize();
+
+            for vertex in [a, b, c] {
+                assert!(vertex.normal.is_normalized());
+                assert!(face_normal.dot(vertex.normal) > 0.99);
+            }
+        }
+    }
+
+    #[test]
+    fn hex_prism_faces_point_outwards() {
+        assert_faces_match_normals(&create_hex_prism(0.5, 0.25));
+    }
+
+    #[test]
+    #[should_panic(expected = "height must be finite")]
+    fn non_finite_height_is_rejected() {
+        create_hex_prism(0.5, f32::NAN);
+    }
+
+    #[test]
+    #[should_panic(expected = "size must be finite")]
+    fn non_finite_size_is_rejected() {
+        create_beveled_hex_prism(f32::INFINITY, 0.25, 0.05, 3);
+    }
+
+    #[test]
+    fn beveled_hex_prism_faces_point_outwards() {
+        for segments in 1..=4 {
+            assert_faces_match_normals(&create_beveled_hex_prism(0.5, 0.25, 0.05, segments));
+        }
+    }
+
+    #[test]
+    fn beveled_hex_prism_keeps_outer_dimensions() {
+        let mesh = create_beveled_hex_prism(0.5, 0.25, 0.05, 3);
+
+        let max_height = mesh
+            .vertices()
+            .iter()
+            .map(|v| v.position.y)
+            .fold(f32::MIN, f32::max);
+        let max_radius = mesh
+            .vertices()
+            .iter()
+            .map(|v| Vec2::new(v.position.x, v.position.z).length())
+            .fold(f32::MIN, f32::max);
+
+        assert!((max_height - 0.25).abs() < 1e-6);
+        assert!((max_radius - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn bevel_has_quad_per_side_and_segment() {
+        let bevel = create_hex_bevel(0.5, 0.25, 0.05, 3);
+
+        assert_eq!(bevel.triangles().len(), 3 * 6 * 2);
+    }
+
+    #[test]
+    fn subdivided_hex_has_expected_size() {
+        for rings in 1..=5 {
+            let mesh = create_subdivided_hex(0.5, rings, Vec3::ZERO, |_| 0.0);
+
+            assert_eq!(mesh.vertices().len() as u32, 3 * rings * (rings + 1) + 1);
+            assert_eq!(mesh.triangles().len() as u32, 6 * rings * rings);
+            assert_faces_match_normals(&mesh);
+        }
+    }
+
+    #[test]
+    fn subdivided_hex_is_displaced_and_shaded() {
+        let slope = |p: Vec3| 0.5 * p.x;
+        let mesh = create_subdivided_hex(0.5, 3, Vec3::new(2.0, 0.0, 0.0), slope);
+        let expected_normal = Vec3::new(-0.5, 1.0, 0.0).normalize();
+
+        for vertex in mesh.vertices() {
+            assert!((vertex.position.y - slope(vertex.position + 2.0 * Vec3::X)).abs() < 1e-5);
+            assert!(vertex.normal.abs_diff_eq(expected_normal, 1e-5));
+        }
+    }
+
+    #[test]
+    fn neighbouring_subdivided_hexes_share_edge_vertices() {
+        use crate::coordinates::{Axial, Cube};
+
+        let wave = |p: Vec3| (3.0 * p.x).sin() * (2.0 * p.z).cos();
+        let world_vertices = |axial: Axial| {
+            let origin = Vec3::from(axial);
+            create_subdivided_hex(0.5, 4, origin, wave)
+                .vertices()
+                .iter()
+                .map(|v| (v.position + origin, v.normal))
+                .collect::<Vec<_>>()
+        };
+
+        let center = world_vertices(Axial::origin());
+
+        for neighbour in Cube::origin().neighbours() {
+            let neighbour = world_vertices(neighbour.into());
+            let shared = center
+                .iter()
+                .filter(|(p, n)| {
+                    neighbour
+                        .iter()
+                        .any(|(q, m)| p.abs_diff_eq(*q, 1e-5) && n.abs_diff_eq(*m, 1e-5))
+                })
+                .count();
+
+            assert_eq!(shared, 4 + 1);
+        }
+    }
+
+    #[test]
+    fn generated_meshes_are_valid() {
+        assert_eq!(create_hex_prism(0.5, 0.25).validate(), Ok(()));
+        assert_eq!(
+            create_beveled_hex_prism(0.5, 0.25, 0.05, 3).validate(),
+            Ok(())
+        );
+        assert_eq!(
+            create_subdivided_hex(0.5, 3, Vec3::ZERO, |p| p.x.sin()).validate(),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn oversized_bevel_is_clamped_to_height() {
+        let mesh = create_beveled_hex_prism(0.5, 0.1, 1.0, 2);
+
+        assert!(mesh.vertices().iter().all(|v| v.position.y > -1e-6));
+        assert_faces_match_normals(&mesh);
+    }
+}
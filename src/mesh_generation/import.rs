@@ -0,0 +1,412 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+use bevy::prelude::{Mesh, Vec2, Vec3, Vec4};
+use bevy::render::mesh::VertexAttributeValues;
+use bevy::render::render_resource::PrimitiveTopology;
+use thiserror::Error;
+
+use super::submesh::{MeshError, SubMesh, Triangle, Vertex, ATTRIBUTE_UV_1};
+
+#[derive(Debug, Error)]
+pub enum ImportError {
+    #[error("Only triangle lists can be imported, got {0:?}")]
+    UnsupportedTopology(PrimitiveTopology),
+    #[error("Mesh has no vertex positions")]
+    MissingPositions,
+    #[error("Attribute {0} has an unsupported format or length")]
+    InvalidAttribute(&'static str),
+    #[error("Index count is not a multiple of three")]
+    IncompleteTriangle,
+    #[error(transparent)]
+    Mesh(#[from] MeshError),
+    #[error("Line {line}: {message}")]
+    Parse { line: usize, message: String },
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+fn float32x2(
+    mesh: &Mesh,
+    attribute: bevy::render::mesh::MeshVertexAttribute,
+    name: &'static str,
+    count: usize,
+) -> Result<Option<Vec<Vec2>>, ImportError> {
+    match mesh.attribute(attribute) {
+        None => Ok(None),
+        Some(VertexAttributeValues::Float32x2(values)) if values.len() == count => {
+            Ok(Some(values.iter().copied().map(Vec2::from).collect()))
+        }
+        Some(_) => Err(ImportError::InvalidAttribute(name)),
+    }
+}
+
+fn float32x3(
+    mesh: &Mesh,
+    attribute: bevy::render::mesh::MeshVertexAttribute,
+    name: &'static str,
+    count: usize,
+) -> Result<Option<Vec<Vec3>>, ImportError> {
+    match mesh.attribute(attribute) {
+        None => Ok(None),
+        Some(VertexAttributeValues::Float32x3(values)) if values.len() == count => {
+            Ok(Some(values.iter().copied().map(Vec3::from).collect()))
+        }
+        Some(_) => Err(ImportError::InvalidAttribute(name)),
+    }
+}
+
+/// Area weighted vertex normals for all vertices where `missing` is set.
+fn fill_missing_normals(vertices: &mut [Vertex], triangles: &[Triangle], missing: &[bool]) {
+    let mut normals = vec![Vec3::ZERO; vertices.len()];
+
+    for triangle in triangles {
+        let [a, b, c] = triangle.indices.map(|i| vertices[i as usize].position);
+        let normal = (b - a).cross(c - a);
+        for i in triangle.indices {
+            normals[i as usize] += normal;
+        }
+    }
+
+    for ((vertex, normal), missing) in vertices.iter_mut().zip(normals).zip(missing) {
+        if *missing {
+            vertex.normal = normal.normalize_or_zero();
+        }
+    }
+}
+
+impl TryFrom<&Mesh> for SubMesh {
+    type Error = ImportError;
+
+    fn try_from(mesh: &Mesh) -> Result<Self, Self::Error> {
+        if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
+            return Err(ImportError::UnsupportedTopology(mesh.primitive_topology()));
+        }
+
+        let positions: Vec<Vec3> = match mesh.attribute(Mesh::ATTRIBUTE_POSITION) {
+            None => return Err(ImportError::MissingPositions),
+            Some(VertexAttributeValues::Float32x3(values)) => {
+                values.iter().copied().map(Vec3::from).collect()
+            }
+            Some(_) => return Err(ImportError::InvalidAttribute("position")),
+        };
+        let count = positions.len();
+
+        let normals = float32x3(mesh, Mesh::ATTRIBUTE_NORMAL, "normal", count)?;
+        let uvs = float32x2(mesh, Mesh::ATTRIBUTE_UV_0, "uv", count)?;
+        let uvs_1 = float32x2(mesh, ATTRIBUTE_UV_1, "uv_1", count)?;
+        let tangents = match mesh.attribute(Mesh::ATTRIBUTE_TANGENT) {
+            None => None,
+            Some(VertexAttributeValues::Float32x4(values)) if values.len() == count => {
+                Some(values.iter().copied().map(Vec4::from).collect::<Vec<_>>())
+            }
+            Some(_) => return Err(ImportError::InvalidAttribute("tangent")),
+        };
+
+        let indices: Vec<u32> = match mesh.indices() {
+            Some(indices) => indices.iter().map(|i| i as u32).collect(),
+            None => (0..count as u32).collect(),
+        };
+        if !indices.chunks_exact(3).remainder().is_empty() {
+            return Err(ImportError::IncompleteTriangle);
+        }
+        let mut vertices: Vec<Vertex> = positions
+            .into_iter()
+            .enumerate()
+            .map(|(i, position)| {
+                let normal = normals.as_ref().map_or(Vec3::ZERO, |n| n[i]);
+                let uv = uvs.as_ref().map_or(Vec2::ZERO, |uv| uv[i]);
+                let mut vertex = Vertex::new(position, normal, uv);
+                vertex.uv_1 = uvs_1.as_ref().map(|uv| uv[i]);
+                vertex.tangent = tangents.as_ref().map(|t| t[i]);
+                vertex
+            })
+            .collect();
+        let triangles: Vec<Triangle> = indices
+            .chunks_exact(3)
+            .map(|t| Triangle::new(t[0], t[1], t[2]))
+            .collect();
+
+        if let Some(error) = SubMesh::index_errors(count, &triangles).next() {
+            return Err(error.into());
+        }
+
+        if normals.is_none() {
+            fill_missing_normals(&mut vertices, &triangles, &vec![true; count]);
+        }
+
+        Ok(SubMesh::new(vertices, triangles)?)
+    }
+}
+
+fn parse_floats<const N: usize>(line: usize, values: &[&str]) -> Result<[f32; N], ImportError> {
+    let mut result = [0.0; N];
+    for (i, slot) in result.iter_mut().enumerate() {
+        let value = values.get(i).ok_or_else(|| ImportError::Parse {
+            line,
+            message: format!("expected {} values", N),
+        })?;
+        *slot = value.parse().map_err(|_| ImportError::Parse {
+            line,
+            message: format!("invalid number `{}`", value),
+        })?;
+    }
+    Ok(result)
+}
+
+/// Resolves a one based, possibly negative (relative to the end) OBJ index.
+fn resolve_index(line: usize, index: &str, len: usize) -> Result<Option<usize>, ImportError> {
+    if index.is_empty() {
+        return Ok(None);
+    }
+
+    let invalid = || ImportError::Parse {
+        line,
+        message: format!("invalid index `{}`", index),
+    };
+
+    let index: i64 = index.parse().map_err(|_| invalid())?;
+    let resolved = match index {
+        i if i > 0 => i - 1,
+        i if i < 0 => len as i64 + i,
+        _ => return Err(invalid()),
+    };
+
+    if resolved < 0 || resolved as usize >= len {
+        return Err(invalid());
+    }
+
+    Ok(Some(resolved as usize))
+}
+
+/// Reads triangles and polygons from a Wavefront OBJ. Polygons are triangulated as fans, all
+/// objects and groups end up in one mesh. Vertices without normals get area weighted face
+/// normals, missing texture coordinates are zero.
+pub fn read_obj<R: BufRead>(reader: R) -> Result<SubMesh, ImportError> {
+    let mut positions = vec![];
+    let mut uvs = vec![];
+    let mut normals = vec![];
+
+    let mut vertex_lookup = HashMap::new();
+    let mut vertices = vec![];
+    let mut missing_normals = vec![];
+    let mut triangles = vec![];
+
+    for (number, line) in reader.lines().enumerate() {
+        let line = line?;
+        let number = number + 1;
+        let mut tokens = line.split_whitespace();
+        let keyword = tokens.next();
+        let values: Vec<&str> = tokens.collect();
+
+        match keyword {
+            Some("v") => {
+                positions.push(Vec3::from(parse_floats::<3>(number, &values)?));
+            }
+            Some("vt") => {
+                let [u, v] = parse_floats::<2>(number, &values)?;
+                uvs.push(Vec2::new(u, 1.0 - v));
+            }
+            Some("vn") => {
+                normals.push(Vec3::from(parse_floats::<3>(number, &values)?));
+            }
+            Some("f") => {
+                let mut face = vec![];
+
+                for corner in values {
+                    let mut parts = corner.split('/');
+                    let position = parts.next().unwrap_or_default();
+                    let uv = parts.next().unwrap_or_default();
+                    let normal = parts.next().unwrap_or_default();
+
+                    let key = (
+                        resolve_index(number, position, positions.len())?.ok_or_else(|| {
+                            ImportError::Parse {
+                                line: number,
+                                message: "face corner without position".into(),
+                            }
+                        })?,
+                        resolve_index(number, uv, uvs.len())?,
+                        resolve_index(number, normal, normals.len())?,
+                    );
+
+                    let index = *vertex_lookup.entry(key).or_insert_with(|| {
+                        let (position, uv, normal) = key;
+                        vertices.push(Vertex::new(
+                            positions[position],
+                            normal.map_or(Vec3::ZERO, |n| normals[n]),
+                            uv.map_or(Vec2::ZERO, |uv| uvs[uv]),
+                        ));
+                        missing_normals.push(normal.is_none());
+                        vertices.len() as u32 - 1
+                    });
+                    face.push(index);
+                }
+
+                if face.len() < 3 {
+                    return Err(ImportError::Parse {
+                        line: number,
+                        message: "face with less than three corners".into(),
+                    });
+                }
+
+                for i in 1..face.len() - 1 {
+                    triangles.push(Triangle::new(face[0], face[i], face[i + 1]));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if missing_normals.iter().any(|m| *m) {
+        fill_missing_normals(&mut vertices, &triangles, &missing_normals);
+    }
+
+    Ok(SubMesh::new(vertices, triangles)?)
+}
+
+pub fn load_obj(path: impl AsRef<Path>) -> Result<SubMesh, ImportError> {
+    read_obj(BufReader::new(File::open(path)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::render::mesh::Indices;
+
+    use super::*;
+    use crate::mesh_generation::{export::write_obj, hex::create_hex_prism};
+
+    fn assert_same_mesh(a: &SubMesh, b: &SubMesh) {
+        assert_eq!(a.vertices().len(), b.vertices().len());
+        assert_eq!(a.triangles().len(), b.triangles().len());
+
+        for (t_a, t_b) in a.triangles().iter().zip(b.triangles()) {
+            for (i_a, i_b) in t_a.indices.iter().zip(t_b.indices) {
+                let v_a = a.vertices()[*i_a as usize];
+                let v_b = b.vertices()[i_b as usize];
+                assert!(v_a.position.abs_diff_eq(v_b.position, 1e-6));
+                assert!(v_a.normal.abs_diff_eq(v_b.normal, 1e-6));
+                assert!(v_a.uv.abs_diff_eq(v_b.uv, 1e-6));
+            }
+        }
+    }
+
+    #[test]
+    fn mesh_round_trip_works() {
+        let original = create_hex_prism(0.5, 0.25).generate_tangents();
+        let mesh = Mesh::from(original.clone());
+
+        let imported = SubMesh::try_from(&mesh).unwrap();
+
+        assert_eq!(imported, original);
+    }
+
+    #[test]
+    fn u16_indices_and_missing_attributes_work() {
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        mesh.insert_attribute(
+            Mesh::ATTRIBUTE_POSITION,
+            vec![[0.0, 0.0, 0.0], [0.0, 0.0, 1.0], [1.0, 0.0, 0.0]],
+        );
+        mesh.set_indices(Some(Indices::U16(vec![0, 1, 2])));
+
+        let imported = SubMesh::try_from(&mesh).unwrap();
+
+        assert_eq!(imported.triangles(), &[Triangle::new(0, 1, 2)]);
+        for vertex in imported.vertices() {
+            assert_eq!(vertex.normal, Vec3::Y);
+            assert_eq!(vertex.uv, Vec2::ZERO);
+            assert_eq!(vertex.tangent, None);
+        }
+    }
+
+    #[test]
+    fn invalid_meshes_are_rejected() {
+        let mesh = Mesh::new(PrimitiveTopology::LineList);
+        assert!(matches!(
+            SubMesh::try_from(&mesh),
+            Err(ImportError::UnsupportedTopology(
+                PrimitiveTopology::LineList
+            ))
+        ));
+
+        let mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        assert!(matches!(
+            SubMesh::try_from(&mesh),
+            Err(ImportError::MissingPositions)
+        ));
+
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, vec![[0.0, 0.0, 0.0]; 3]);
+        mesh.set_indices(Some(Indices::U32(vec![0, 1, 3])));
+        assert!(matches!(
+            SubMesh::try_from(&mesh),
+            Err(ImportError::Mesh(MeshError::IndexOutOfRange {
+                triangle: 0,
+                index: 3
+            }))
+        ));
+    }
+
+    #[test]
+    fn obj_round_trip_works() {
+        let original = create_hex_prism(0.5, 0.25);
+        let mut obj = vec![];
+        write_obj(&original, &mut obj).unwrap();
+
+        let imported = read_obj(obj.as_slice()).unwrap();
+
+        assert_same_mesh(&imported, &original);
+    }
+
+    #[test]
+    fn obj_polygons_and_relative_indices_work() {
+        let obj = "\
+# quad without normals and uvs
+v 0 0 0
+v 1 0 0
+v 1 0 -1
+v 0 0 -1
+f -4 -3 -2 -1
+";
+
+        let imported = read_obj(obj.as_bytes()).unwrap();
+
+        assert_eq!(
+            imported.triangles(),
+            &[Triangle::new(0, 1, 2), Triangle::new(0, 2, 3)]
+        );
+        for vertex in imported.vertices() {
+            assert!(vertex.normal.abs_diff_eq(Vec3::Y, 1e-6));
+        }
+    }
+
+    #[test]
+    fn obj_errors_report_line() {
+        let obj = "v 0 0 0\nv 1 0 0\nf 1 2 3\n";
+
+        let error = read_obj(obj.as_bytes()).unwrap_err();
+
+        assert!(matches!(error, ImportError::Parse { line: 3, .. }));
+    }
+
+    #[test]
+    fn imported_mesh_merges_with_generated_hex() {
+        let prop = read_obj("v 0 0 0\nv 0 1 0\nv 1 0 0\nf 1 2 3\n".as_bytes()).unwrap();
+        let hex = create_hex_prism(0.5, 0.25);
+        let hex_vertices = hex.vertices().len() as u32;
+
+        let chunk = hex.merge(prop.translate(Vec3::Y * 0.25).unwrap());
+
+        assert_eq!(
+            chunk.triangles().last(),
+            Some(&Triangle::new(
+                hex_vertices,
+                hex_vertices + 1,
+                hex_vertices + 2
+            ))
+        );
+    }
+}
@@ -0,0 +1,399 @@
+use bevy::prelude::Vec3;
+
+use super::submesh::SubMesh;
+
+/// Triangles per BVH leaf. Splitting further costs more in box tests than it saves.
+const BVH_LEAF_SIZE: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    pub fn from_points<I>(points: I) -> Option<Aabb>
+    where
+        I: IntoIterator<Item = Vec3>,
+    {
+        let mut points = points.into_iter();
+        let first = points.next()?;
+
+        Some(points.fold(
+            Aabb {
+                min: first,
+                max: first,
+            },
+            |aabb, point| Aabb {
+                min: aabb.min.min(point),
+                max: aabb.max.max(point),
+            },
+        ))
+    }
+
+    pub fn union(self, other: Aabb) -> Aabb {
+        Aabb {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    pub fn centre(&self) -> Vec3 {
+        0.5 * (self.min + self.max)
+    }
+
+    pub fn size(&self) -> Vec3 {
+        self.max - self.min
+    }
+
+    pub fn contains(&self, point: Vec3) -> bool {
+        point.cmpge(self.min).all() && point.cmple(self.max).all()
+    }
+
+    /// Distance along the ray at which it enters the box, 0 if it starts inside. `direction`
+    /// has to be normalized for the distance to be in world units.
+    pub fn intersect_ray(&self, origin: Vec3, direction: Vec3) -> Option<f32> {
+        // Slab test. Division by zero gives infinities, which the min/max handle correctly
+        // unless the origin lies exactly on a slab, where NaNs are ignored by f32::max/min.
+        let inverse = direction.recip();
+        let t1 = (self.min - origin) * inverse;
+        let t2 = (self.max - origin) * inverse;
+
+        let near = t1.min(t2).max_element().max(0.0);
+        let far = t1.max(t2).min_element();
+
+        (near <= far).then_some(near)
+    }
+}
+
+/// Result of a ray hitting a `SubMesh`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RayHit {
+    /// Index of the triangle in `SubMesh::triangles`.
+    pub triangle: usize,
+    /// Weights of the three corners of the triangle at the hit point.
+    pub barycentric: Vec3,
+    /// Distance from the ray origin in world units.
+    pub distance: f32,
+}
+
+/// Möller–Trumbore ray triangle intersection, hitting both sides of the triangle. Returns the
+/// distance along `direction` and the barycentric weights of the hit point.
+pub fn intersect_triangle(
+    origin: Vec3,
+    direction: Vec3,
+    [a, b, c]: [Vec3; 3],
+) -> Option<(f32, Vec3)> {
+    const EPSILON: f32 = 1e-7;
+
+    let edge_1 = b - a;
+    let edge_2 = c - a;
+    let p = direction.cross(edge_2);
+    let determinant = edge_1.dot(p);
+
+    if determinant.abs() < EPSILON {
+        return None;
+    }
+
+    let inverse = 1.0 / determinant;
+    let to_origin = origin - a;
+
+    let u = to_origin.dot(p) * inverse;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+
+    let q = to_origin.cross(edge_1);
+    let v = direction.dot(q) * inverse;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+
+    let distance = edge_2.dot(q) * inverse;
+    (distance >= 0.0).then(|| (distance, Vec3::new(1.0 - u - v, u, v)))
+}
+
+impl SubMesh {
+    fn triangle_positions(&self, triangle: usize) -> [Vec3; 3] {
+        self.triangles[triangle]
+            .indices
+            .map(|i| self.vertices[i as usize].position)
+    }
+
+    /// Bounding box of all vertices, `None` for an empty mesh.
+    pub fn aabb(&self) -> Option<Aabb> {
+        Aabb::from_points(self.vertices.iter().map(|v| v.position))
+    }
+
+    /// Closest triangle hit by the ray, testing every triangle. For repeated queries against
+    /// large meshes build a `Bvh` instead.
+    pub fn intersect_ray(&self, origin: Vec3, direction: Vec3) -> Option<RayHit> {
+        let direction = direction.try_normalize()?;
+
+        (0..self.triangles.len())
+            .filter_map(|triangle| {
+                intersect_triangle(origin, direction, self.triangle_positions(triangle)).map(
+                    |(distance, barycentric)| RayHit {
+                        triangle,
+                        barycentric,
+                        distance,
+                    },
+                )
+            })
+            .min_by(|a, b| a.distance.total_cmp(&b.distance))
+    }
+}
+
+#[derive(Clone, Debug)]
+enum BvhNode {
+    Leaf {
+        aabb: Aabb,
+        first: usize,
+        count: usize,
+    },
+    Branch {
+        aabb: Aabb,
+        left: usize,
+        right: usize,
+    },
+}
+
+impl BvhNode {
+    fn aabb(&self) -> &Aabb {
+        match self {
+            BvhNode::Leaf { aabb, .. } | BvhNode::Branch { aabb, .. } => aabb,
+        }
+    }
+}
+
+/// Bounding volume hierarchy over the triangles of a `SubMesh` for fast ray queries. It keeps
+/// its own copy of the triangle positions, so it has to be rebuilt when the mesh changes.
+#[derive(Clone, Debug)]
+pub struct Bvh {
+    nodes: Vec<BvhNode>,
+    /// Triangle positions in the order the leaves refer to them, with their original index.
+    triangles: Vec<([Vec3; 3], usize)>,
+}
+
+impl Bvh {
+    pub fn new(mesh: &SubMesh) -> Bvh {
+        let mut bvh = Bvh {
+            nodes: vec![],
+            triangles: (0..mesh.triangles.len())
+                .map(|triangle| (mesh.triangle_positions(triangle), triangle))
+                .collect(),
+        };
+
+        if !bvh.triangles.is_empty() {
+            bvh.build(0, bvh.triangles.len());
+        }
+
+        bvh
+    }
+
+    fn bounds(&self, first: usize, count: usize) -> Aabb {
+        Aabb::from_points(
+            self.triangles[first..first + count]
+                .iter()
+                .flat_map(|(positions, _)| *positions),
+        )
+        .unwrap()
+    }
+
+    /// Builds the node for `count` triangles starting at `first` and returns its index.
+    fn build(&mut self, first: usize, count: usize) -> usize {
+        let aabb = self.bounds(first, count);
+        let index = self.nodes.len();
+        self.nodes.push(BvhNode::Leaf { aabb, first, count });
+
+        if count <= BVH_LEAF_SIZE {
+            return index;
+        }
+
+        // Median split along the axis in which the triangle centres are spread the most.
+        let centre = |positions: &[Vec3; 3]| (positions[0] + positions[1] + positions[2]) / 3.0;
+        let centres = Aabb::from_points(
+            self.triangles[first..first + count]
+                .iter()
+                .map(|(positions, _)| centre(positions)),
+        )
+        .unwrap()
+        .size();
+        let axis = if centres.x >= centres.y && centres.x >= centres.z {
+            0
+        } else if centres.y >= centres.z {
+            1
+        } else {
+            2
+        };
+
+        let half = count / 2;
+        self.triangles[first..first + count].select_nth_unstable_by(half, |a, b| {
+            centre(&a.0)[axis].total_cmp(&centre(&b.0)[axis])
+        });
+
+        let left = self.build(first, half);
+        let right = self.build(first + half, count - half);
+        self.nodes[index] = BvhNode::Branch { aabb, left, right };
+
+        index
+    }
+
+    pub fn aabb(&self) -> Option<Aabb> {
+        self.nodes.first().map(|node| *node.aabb())
+    }
+
+    /// Closest triangle hit by the ray, same as `SubMesh::intersect_ray` on the mesh the
+    /// hierarchy was built from.
+    pub fn intersect_ray(&self, origin: Vec3, direction: Vec3) -> Option<RayHit> {
+        let direction = direction.try_normalize()?;
+        let mut closest: Option<RayHit> = None;
+        let mut stack = vec![];
+
+        if !self.nodes.is_empty() {
+            stack.push(0);
+        }
+
+        while let Some(node) = stack.pop() {
+            let node = &self.nodes[node];
+            let entry = match node.aabb().intersect_ray(origin, direction) {
+                Some(entry) => entry,
+                None => continue,
+            };
+            if closest.is_some_and(|hit| hit.distance < entry) {
+                continue;
+            }
+
+            match *node {
+                BvhNode::Leaf { first, count, .. } => {
+                    for (positions, triangle) in &self.triangles[first..first + count] {
+                        if let Some((distance, barycentric)) =
+                            intersect_triangle(origin, direction, *positions)
+                        {
+                            if !closest.is_some_and(|hit| hit.distance <= distance) {
+                                closest = Some(RayHit {
+                                    triangle: *triangle,
+                                    barycentric,
+                                    distance,
+                                });
+                            }
+                        }
+                    }
+                }
+                BvhNode::Branch { left, right, .. } => {
+                    stack.push(left);
+                    stack.push(right);
+                }
+            }
+        }
+
+        closest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coordinates::{Axial, Cube};
+    use crate::mesh_generation::hex::{create_hex, create_hex_prism};
+    use crate::mesh_generation::lod::create_hex_chunk;
+
+    #[test]
+    fn aabb_of_hex_prism() {
+        let aabb = create_hex_prism(0.5, 1.0).aabb().unwrap();
+
+        let half_width = 0.5 * (30f32).to_radians().cos();
+        assert!(aabb
+            .min
+            .abs_diff_eq(Vec3::new(-half_width, 0.0, -0.5), 1e-6));
+        assert!(aabb.max.abs_diff_eq(Vec3::new(half_width, 1.0, 0.5), 1e-6));
+        assert!(SubMesh::new(vec![], vec![]).unwrap().aabb().is_none());
+    }
+
+    #[test]
+    fn aabb_ray_test() {
+        let aabb = Aabb {
+            min: Vec3::splat(-1.0),
+            max: Vec3::splat(1.0),
+        };
+
+        assert_eq!(
+            aabb.intersect_ray(Vec3::new(0.0, 5.0, 0.0), -Vec3::Y),
+            Some(4.0)
+        );
+        assert_eq!(aabb.intersect_ray(Vec3::ZERO, Vec3::X), Some(0.0));
+        assert_eq!(aabb.intersect_ray(Vec3::new(0.0, 5.0, 0.0), Vec3::Y), None);
+        assert_eq!(aabb.intersect_ray(Vec3::new(2.0, 5.0, 0.0), -Vec3::Y), None);
+    }
+
+    #[test]
+    fn ray_hits_hex_top() {
+        let mesh = create_hex(0.5);
+
+        let hit = mesh
+            .intersect_ray(Vec3::new(0.1, 2.0, 0.05), -Vec3::Y)
+            .unwrap();
+
+        assert!((hit.distance - 2.0).abs() < 1e-6);
+        assert!((hit.barycentric.x + hit.barycentric.y + hit.barycentric.z - 1.0).abs() < 1e-6);
+
+        let corners = mesh.triangles()[hit.triangle]
+            .indices
+            .map(|i| mesh.vertices()[i as usize].position);
+        let point = hit.barycentric.x * corners[0]
+            + hit.barycentric.y * corners[1]
+            + hit.barycentric.z * corners[2];
+        assert!(point.abs_diff_eq(Vec3::new(0.1, 0.0, 0.05), 1e-6));
+
+        assert!(mesh
+            .intersect_ray(Vec3::new(1.0, 2.0, 0.0), -Vec3::Y)
+            .is_none());
+    }
+
+    #[test]
+    fn ray_returns_closest_hit() {
+        let mesh = create_hex_prism(0.5, 1.0);
+
+        let hit = mesh
+            .intersect_ray(Vec3::new(0.0, 3.0, 0.0), -Vec3::Y)
+            .unwrap();
+
+        assert!((hit.distance - 2.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn bvh_matches_brute_force() {
+        let cubes: Vec<_> = (-4..=4)
+            .flat_map(|q| (-4..=4).map(move |r| Cube::from(Axial::new(q, r))))
+            .collect();
+        let mesh = create_hex_chunk(&cubes, 0.5, 0.25);
+        let bvh = Bvh::new(&mesh);
+
+        assert_eq!(bvh.aabb(), mesh.aabb());
+
+        for i in 0..50 {
+            let x = (i as f32 * 0.37).sin() * 4.0;
+            let z = (i as f32 * 0.73).cos() * 4.0;
+            let origin = Vec3::new(x, 5.0, z);
+            let direction = Vec3::new(0.1 * (i % 3) as f32, -1.0, -0.05 * (i % 5) as f32);
+
+            let expected = mesh.intersect_ray(origin, direction);
+            let actual = bvh.intersect_ray(origin, direction);
+
+            match (expected, actual) {
+                (Some(expected), Some(actual)) => {
+                    assert!((expected.distance - actual.distance).abs() < 1e-5)
+                }
+                (expected, actual) => assert_eq!(expected, actual),
+            }
+        }
+    }
+
+    #[test]
+    fn empty_bvh_hits_nothing() {
+        let bvh = Bvh::new(&SubMesh::new(vec![], vec![]).unwrap());
+
+        assert!(bvh.aabb().is_none());
+        assert!(bvh.intersect_ray(Vec3::ZERO, Vec3::Y).is_none());
+    }
+}
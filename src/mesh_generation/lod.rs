@@ -0,0 +1,612 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+use bevy::prelude::{Vec2, Vec3};
+
+use crate::coordinates::{Axial, Cube, Direction, SIZE};
+
+use super::hex::{create_hex_prism, get_hex_point, get_hex_side_normal};
+use super::submesh::{SubMesh, Triangle, Vertex, INDICES_IN_RANGE};
+
+/// Positions closer than this are treated as one point during simplification.
+const SIMPLIFY_EPSILON: f32 = 1e-5;
+
+/// Edges on the mesh border are protected by this much stronger quadric, so outlines survive
+/// longer than interior detail.
+const BOUNDARY_WEIGHT: f64 = 100.0;
+
+/// Symmetric 4x4 error quadric, stored as the upper triangle.
+#[derive(Clone, Copy, Default)]
+struct Quadric([f64; 10]);
+
+impl Quadric {
+    fn from_plane(normal: Vec3, point: Vec3, weight: f64) -> Self {
+        let [a, b, c] = normal.to_array().map(f64::from);
+        let d = -f64::from(normal.dot(point));
+
+        Quadric(
+            [
+                a * a,
+                a * b,
+                a * c,
+                a * d,
+                b * b,
+                b * c,
+                b * d,
+                c * c,
+                c * d,
+                d * d,
+            ]
+            .map(|q| q * weight),
+        )
+    }
+
+    fn add(self, other: Quadric) -> Quadric {
+        let mut sum = self.0;
+        sum.iter_mut().zip(other.0).for_each(|(a, b)| *a += b);
+        Quadric(sum)
+    }
+
+    fn error(&self, p: Vec3) -> f64 {
+        let [x, y, z] = p.to_array().map(f64::from);
+        let q = &self.0;
+
+        q[0] * x * x
+            + 2.0 * q[1] * x * y
+            + 2.0 * q[2] * x * z
+            + 2.0 * q[3] * x
+            + q[4] * y * y
+            + 2.0 * q[5] * y * z
+            + 2.0 * q[6] * y
+            + q[7] * z * z
+            + 2.0 * q[8] * z
+            + q[9]
+    }
+}
+
+struct Simplifier {
+    positions: Vec<Vec3>,
+    quadrics: Vec<Quadric>,
+    versions: Vec<u32>,
+    removed: Vec<bool>,
+    faces: Vec<Option<[usize; 3]>>,
+    adjacency: Vec<Vec<usize>>,
+}
+
+impl Simplifier {
+    fn face_normal(&self, face: [usize; 3]) -> Vec3 {
+        let [a, b, c] = face.map(|i| self.positions[i]);
+        (b - a).cross(c - a)
+    }
+
+    fn neighbours(&self, vertex: usize) -> Vec<usize> {
+        let mut neighbours: Vec<usize> = self.adjacency[vertex]
+            .iter()
+            .filter_map(|f| self.faces[*f])
+            .flatten()
+            .filter(|v| *v != vertex)
+            .collect();
+        neighbours.sort_unstable();
+        neighbours.dedup();
+        neighbours
+    }
+
+    /// Cheapest of keeping `a`, keeping `b` or meeting in the middle.
+    fn collapse_cost(&self, a: usize, b: usize) -> (f64, Vec3) {
+        let quadric = self.quadrics[a].add(self.quadrics[b]);
+        let (pa, pb) = (self.positions[a], self.positions[b]);
+
+        [pa, pb, 0.5 * (pa + pb)]
+            .into_iter()
+            .map(|p| (quadric.error(p).max(0.0), p))
+            .fold((f64::MAX, pa), |best, candidate| {
+                if candidate.0 < best.0 {
+                    candidate
+                } else {
+                    best
+                }
+            })
+    }
+
+    fn is_valid_collapse(&self, a: usize, b: usize, target: Vec3) -> bool {
+        // Link condition: the only vertices shared by both ends may be the ones opposite the
+        // edge, otherwise the collapse would create duplicate or non-manifold faces.
+        let shared_faces = self.adjacency[a]
+            .iter()
+            .filter(|f| self.faces[**f].is_some_and(|face| face.contains(&b)))
+            .count();
+        let neighbours_b = self.neighbours(b);
+        let shared_neighbours = self
+            .neighbours(a)
+            .iter()
+            .filter(|n| neighbours_b.binary_search(n).is_ok())
+            .count();
+        if shared_neighbours > shared_faces {
+            return false;
+        }
+
+        // The faces that survive must not flip or collapse.
+        self.adjacency[a]
+            .iter()
+            .chain(&self.adjacency[b])
+            .filter_map(|f| self.faces[*f])
+            .filter(|face| !(face.contains(&a) && face.contains(&b)))
+            .all(|face| {
+                let before = self.face_normal(face);
+                let moved = face.map(|v| if v == a || v == b { a } else { v });
+                let [p0, p1, p2] = moved.map(|v| if v == a { target } else { self.positions[v] });
+                let after = (p1 - p0).cross(p2 - p0);
+
+                after.length_squared() > f32::EPSILON * before.length_squared()
+                    && after.dot(before) > 0.0
+            })
+    }
+
+    fn collapse(&mut self, a: usize, b: usize, target: Vec3) -> usize {
+        self.positions[a] = target;
+        self.quadrics[a] = self.quadrics[a].add(self.quadrics[b]);
+        self.versions[a] += 1;
+        self.removed[b] = true;
+
+        let mut removed_faces = 0;
+        for f in std::mem::take(&mut self.adjacency[b]) {
+            let Some(face) = self.faces[f] else {
+                continue;
+            };
+
+            if face.contains(&a) {
+                self.faces[f] = None;
+                removed_faces += 1;
+                for v in face {
+                    self.adjacency[v].retain(|other| *other != f);
+                }
+            } else {
+                self.faces[f] = Some(face.map(|v| if v == b { a } else { v }));
+                self.adjacency[a].push(f);
+            }
+        }
+
+        removed_faces
+    }
+}
+
+impl SubMesh {
+    /// Reduces the mesh to at most `target_triangles` triangles by repeatedly collapsing the edge
+    /// with the smallest quadric error. Vertices at the same position are collapsed together, so
+    /// hard edges and texture seams don't tear open. Stops early if no more edges can be
+    /// collapsed without flipping faces. The result only depends on the input mesh.
+    pub fn simplify(self, target_triangles: usize) -> SubMesh {
+        if self.triangles.len() <= target_triangles {
+            return self;
+        }
+
+        let representatives =
+            SubMesh::find_representatives(&self.vertices, SIMPLIFY_EPSILON, |_, _| true);
+
+        let mut point_of_vertex = vec![0; self.vertices.len()];
+        let mut positions = vec![];
+        for (vertex, representative) in representatives.iter().enumerate() {
+            if vertex == *representative {
+                point_of_vertex[vertex] = positions.len();
+                positions.push(self.vertices[vertex].position);
+            } else {
+                point_of_vertex[vertex] = point_of_vertex[*representative];
+            }
+        }
+
+        let faces: Vec<Option<[usize; 3]>> = self
+            .triangles
+            .iter()
+            .map(|t| t.indices.map(|i| point_of_vertex[i as usize]))
+            .map(|[a, b, c]| (a != b && b != c && a != c).then_some([a, b, c]))
+            .collect();
+
+        let point_count = positions.len();
+        let mut simplifier = Simplifier {
+            positions,
+            quadrics: vec![Quadric::default(); point_count],
+            versions: vec![0; point_count],
+            removed: vec![false; point_count],
+            faces,
+            adjacency: vec![vec![]; point_count],
+        };
+
+        let mut edge_faces: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+        for (f, face) in simplifier.faces.iter().enumerate() {
+            let Some(face) = face else {
+                continue;
+            };
+
+            let normal = simplifier.face_normal(*face);
+            let area = 0.5 * f64::from(normal.length());
+            let plane =
+                Quadric::from_plane(normal.normalize(), simplifier.positions[face[0]], area);
+
+            for k in 0..3 {
+                let (a, b) = (face[k], face[(k + 1) % 3]);
+                simplifier.quadrics[a] = simplifier.quadrics[a].add(plane);
+                simplifier.adjacency[a].push(f);
+                edge_faces.entry((a.min(b), a.max(b))).or_default().push(f);
+            }
+        }
+
+        let mut edges: Vec<_> = edge_faces.into_iter().collect();
+        edges.sort_unstable_by_key(|(edge, _)| *edge);
+
+        for ((a, b), edge_faces) in &edges {
+            if let [f] = edge_faces.as_slice() {
+                let face_normal = simplifier.face_normal(
+                    simplifier.faces[*f].expect("no face is removed before simplifying"),
+                );
+                let edge = simplifier.positions[*b] - simplifier.positions[*a];
+                let border_normal = edge.cross(face_normal).normalize_or_zero();
+                let weight = BOUNDARY_WEIGHT * f64::from(edge.length_squared());
+                let border = Quadric::from_plane(border_normal, simplifier.positions[*a], weight);
+
+                simplifier.quadrics[*a] = simplifier.quadrics[*a].add(border);
+                simplifier.quadrics[*b] = simplifier.quadrics[*b].add(border);
+            }
+        }
+
+        let mut heap = BinaryHeap::new();
+        let push_edge = |heap: &mut BinaryHeap<_>, simplifier: &Simplifier, a: usize, b: usize| {
+            let (a, b) = (a.min(b), a.max(b));
+            let (cost, _) = simplifier.collapse_cost(a, b);
+            let versions = (simplifier.versions[a], simplifier.versions[b]);
+            heap.push(Reverse((cost.to_bits(), a, b, versions)));
+        };
+
+        for ((a, b), _) in &edges {
+            push_edge(&mut heap, &simplifier, *a, *b);
+        }
+
+        let mut live_faces = simplifier.faces.iter().flatten().count();
+
+        while live_faces > target_triangles {
+            let Some(Reverse((_, a, b, versions))) = heap.pop() else {
+                break;
+            };
+
+            if simplifier.removed[a]
+                || simplifier.removed[b]
+                || versions != (simplifier.versions[a], simplifier.versions[b])
+            {
+                continue;
+            }
+
+            let (_, target) = simplifier.collapse_cost(a, b);
+            if !simplifier.is_valid_collapse(a, b, target) {
+                continue;
+            }
+
+            live_faces -= simplifier.collapse(a, b, target);
+
+            for neighbour in simplifier.neighbours(a) {
+                push_edge(&mut heap, &simplifier, a, neighbour);
+            }
+        }
+
+        let mut lookup = HashMap::new();
+        let mut vertices = vec![];
+        let mut triangles = vec![];
+
+        for (triangle, face) in self.triangles.iter().zip(&simplifier.faces) {
+            let Some(face) = face else {
+                continue;
+            };
+
+            let indices = [0, 1, 2].map(|k| {
+                let original = triangle.indices[k] as usize;
+                *lookup.entry((original, face[k])).or_insert_with(|| {
+                    vertices.push(Vertex {
+                        position: simplifier.positions[face[k]],
+                        ..self.vertices[original]
+                    });
+                    vertices.len() as u32 - 1
+                })
+            });
+            triangles.push(Triangle { indices });
+        }
+
+        SubMesh {
+            vertices,
+            triangles,
+        }
+    }
+}
+
+/// All hexes as full prisms in one mesh, each placed at the world position of its coordinate.
+pub fn create_hex_chunk(cubes: &[Cube], size: f32, height: f32) -> SubMesh {
+    create_elevated_hex_chunk(&with_height(cubes, height), size)
+}
+
+/// Like `create_hex_chunk`, with a prism height for every hex.
+pub fn create_elevated_hex_chunk(tiles: &[(Cube, f32)], size: f32) -> SubMesh {
+    tiles
+        .iter()
+        .map(|(cube, height)| {
+            create_hex_prism(size, *height)
+                .translate(Axial::from(*cube).into())
+                .expect("tile positions are finite")
+        })
+        .fold(SubMesh::empty(), SubMesh::merge)
+}
+
+/// Coarse version of `create_hex_chunk`. Level 0 is the full chunk. Higher levels build the
+/// chunk as one closed surface, see `create_chunk_surface`, and simplify it, each level to a
+/// quarter of the triangles of the one before. The surface fills the gaps between tiles, as
+/// `size` only applies to level 0.
+pub fn create_hex_chunk_lod(cubes: &[Cube], size: f32, height: f32, level: u8) -> SubMesh {
+    create_elevated_hex_chunk_lod(&with_height(cubes, height), size, level)
+}
+
+/// Coarse version of `create_elevated_hex_chunk`, see `create_hex_chunk_lod`.
+pub fn create_elevated_hex_chunk_lod(tiles: &[(Cube, f32)], size: f32, level: u8) -> SubMesh {
+    if level == 0 {
+        return create_elevated_hex_chunk(tiles, size);
+    }
+
+    let surface = create_chunk_surface(tiles);
+
+    let target = surface
+        .triangles()
+        .len()
+        .checked_shr(2 * level as u32)
+        .unwrap_or(0);
+    surface.simplify(target.max(1))
+}
+
+/// Tops and walls of all tiles as one surface without holes. The tops are built at the layout
+/// size, so neighbouring tops share their corners and the simplification can merge across
+/// tiles. Walls fill the steps between tiles of different heights. Hexes without a tile count as
+/// height 0, so the border of the chunk gets a skirt down to the ground.
+fn create_chunk_surface(tiles: &[(Cube, f32)]) -> SubMesh {
+    let heights: HashMap<Cube, f32> = tiles.iter().copied().collect();
+    let height_at = |cube: Cube| heights.get(&cube).copied().unwrap_or(0.0);
+
+    // Triangles with the direction they face, they are flipped to match it.
+    let mut faces: Vec<([Vec3; 3], Vec3)> = vec![];
+
+    for (cube, height) in tiles {
+        let centre = Vec3::from(Axial::from(*cube));
+        let corner = |corner: u8, y: f32| centre + get_hex_point(corner % 6, SIZE) + y * Vec3::Y;
+        let neighbour = |side: u8| height_at(*cube + Direction::from_side_index(side));
+
+        for side in 0..6 {
+            let top = centre + *height * Vec3::Y;
+            faces.push((
+                [top, corner(side, *height), corner(side + 1, *height)],
+                Vec3::Y,
+            ));
+        }
+
+        // The higher of two tiles builds the wall between them.
+        for side in 0..6 {
+            let lower = neighbour(side);
+            if lower >= *height {
+                continue;
+            }
+
+            // Heights of the third tile at each end of the wall split its vertical edges, so the
+            // walls meeting at a corner share their vertices.
+            let steps = |third: f32| {
+                let mut steps = vec![lower];
+                if third > lower && third < *height {
+                    steps.push(third);
+                }
+                steps.push(*height);
+                steps
+            };
+            let left = steps(neighbour(side + 5));
+            let right = steps(neighbour(side + 1));
+
+            let outward = get_hex_side_normal(side);
+            let (mut i, mut j) = (0, 0);
+            while i + 1 < left.len() || j + 1 < right.len() {
+                let base = [corner(side, left[i]), corner(side + 1, right[j])];
+                let third = if j + 1 == right.len()
+                    || (i + 1 < left.len() && left[i + 1] <= right[j + 1])
+                {
+                    i += 1;
+                    corner(side, left[i])
+                } else {
+                    j += 1;
+                    corner(side + 1, right[j])
+                };
+                faces.push(([base[0], base[1], third], outward));
+            }
+        }
+    }
+
+    let mut vertices = vec![];
+    let mut triangles = vec![];
+    for (mut positions, outward) in faces {
+        let [a, b, c] = positions;
+        let mut normal = (b - a).cross(c - a).normalize();
+        if normal.dot(outward) < 0.0 {
+            positions.swap(1, 2);
+            normal = -normal;
+        }
+
+        let offset = vertices.len() as u32;
+        vertices.extend(positions.map(|position| {
+            let uv = Vec2::new(position.x, position.z) / (2.0 * SIZE) + Vec2::splat(0.5);
+            Vertex::new(position, normal, uv)
+        }));
+        triangles.push(Triangle::new(offset, offset + 1, offset + 2));
+    }
+
+    SubMesh::new(vertices, triangles).expect(INDICES_IN_RANGE)
+}
+
+fn with_height(cubes: &[Cube], height: f32) -> Vec<(Cube, f32)> {
+    cubes.iter().map(|cube| (*cube, height)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh_generation::hex::create_subdivided_hex;
+
+    fn bounds(mesh: &SubMesh) -> (Vec3, Vec3) {
+        mesh.vertices().iter().map(|v| v.position).fold(
+            (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)),
+            |(min, max), p| (min.min(p), max.max(p)),
+        )
+    }
+
+    fn chunk() -> Vec<Cube> {
+        std::iter::once(Cube::origin())
+            .chain(Cube::origin().neighbours())
+            .collect()
+    }
+
+    #[test]
+    fn simplify_flat_hex_keeps_outline() {
+        let hex = create_subdivided_hex(0.5, 4, Vec3::ZERO, |_| 0.0);
+        let original_bounds = bounds(&hex);
+
+        let simplified = hex.simplify(6);
+
+        assert!(simplified.triangles().len() <= 6);
+        assert!(simplified.triangles().len() >= 4);
+        let (min, max) = bounds(&simplified);
+        assert!(min.abs_diff_eq(original_bounds.0, 1e-5));
+        assert!(max.abs_diff_eq(original_bounds.1, 1e-5));
+        for triangle in simplified.triangles() {
+            let [a, b, c] = triangle
+                .indices
+                .map(|i| simplified.vertices()[i as usize].position);
+            assert!((b - a).cross(c - a).y > 0.0);
+        }
+        assert_eq!(simplified.validate(), Ok(()));
+    }
+
+    #[test]
+    fn simplify_is_deterministic() {
+        let wave = |p: Vec3| 0.1 * (4.0 * p.x).sin() * (3.0 * p.z).cos();
+        let hex = create_subdivided_hex(0.5, 5, Vec3::ZERO, wave);
+
+        let first = hex.clone().simplify(40);
+        let second = hex.simplify(40);
+
+        assert_eq!(first, second);
+        assert!(first.triangles().len() <= 40);
+    }
+
+    #[test]
+    fn simplify_keeps_small_meshes() {
+        let prism = create_hex_prism(0.5, 0.25);
+
+        assert_eq!(prism.clone().simplify(1000), prism);
+    }
+
+    /// Tile size the world uses, which leaves gaps between the tiles.
+    const TILE_SIZE: f32 = 0.49;
+
+    fn mixed_heights() -> Vec<(Cube, f32)> {
+        Cube::origin()
+            .range(2)
+            .enumerate()
+            .map(|(i, cube)| (cube, 0.25 + 0.1 * (i % 4) as f32))
+            .collect()
+    }
+
+    /// Number of triangles using each edge, by position.
+    fn edge_uses(mesh: &SubMesh) -> HashMap<[[i32; 3]; 2], usize> {
+        let key = |p: Vec3| (p * 1e4).round().to_array().map(|c| c as i32);
+        let mut uses = HashMap::new();
+        for triangle in mesh.triangles() {
+            let [a, b, c] = triangle
+                .indices
+                .map(|i| key(mesh.vertices()[i as usize].position));
+            for (p, q) in [(a, b), (b, c), (c, a)] {
+                *uses.entry([p.min(q), p.max(q)]).or_default() += 1;
+            }
+        }
+        uses
+    }
+
+    #[test]
+    fn chunk_lod_reduces_triangles() {
+        let tiles = mixed_heights();
+
+        let counts: Vec<usize> = (0..3)
+            .map(|level| {
+                create_elevated_hex_chunk_lod(&tiles, TILE_SIZE, level)
+                    .triangles()
+                    .len()
+            })
+            .collect();
+
+        assert_eq!(counts[0], tiles.len() * 24);
+        assert!(counts[1] <= create_chunk_surface(&tiles).triangles().len() / 4);
+        assert!(counts[2] < counts[1]);
+    }
+
+    #[test]
+    fn coarse_flat_chunk_merges_tiles() {
+        let coarse = create_hex_chunk_lod(&chunk(), TILE_SIZE, 0.25, 1);
+        let tops = coarse
+            .triangles()
+            .iter()
+            .filter(|triangle| {
+                let [a, b, c] = triangle
+                    .indices
+                    .map(|i| coarse.vertices()[i as usize].position);
+                (b - a).cross(c - a).y > 0.0
+            })
+            .count();
+
+        // Separate hexes need at least four triangles each to keep their outline.
+        assert!(tops < 7 * 4);
+    }
+
+    #[test]
+    fn coarse_chunk_has_no_holes() {
+        let tiles = mixed_heights();
+
+        for level in 1..3 {
+            let mesh = create_elevated_hex_chunk_lod(&tiles, TILE_SIZE, level);
+
+            for ([p, q], uses) in edge_uses(&mesh) {
+                // Only the bottom of the skirt around the chunk is open.
+                let open = p[1] == 0 && q[1] == 0;
+                assert_eq!(uses, if open { 1 } else { 2 }, "{:?} {:?}", p, q);
+            }
+            assert_eq!(mesh.validate(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn chunk_lod_stays_within_bounds() {
+        let tiles = mixed_heights();
+        let (min, max) = bounds(&create_elevated_hex_chunk_lod(&tiles, TILE_SIZE, 0));
+        // Coarse levels close the gaps between the tiles.
+        let margin = Vec3::new(SIZE - TILE_SIZE, 0.0, SIZE - TILE_SIZE) + Vec3::splat(1e-5);
+
+        for level in 1..3 {
+            let (lod_min, lod_max) =
+                bounds(&create_elevated_hex_chunk_lod(&tiles, TILE_SIZE, level));
+            assert!(lod_min.cmpge(min - margin).all());
+            assert!(lod_max.cmple(max + margin).all());
+        }
+    }
+
+    #[test]
+    fn elevated_chunk_keeps_tile_heights() {
+        let tiles: Vec<_> = chunk()
+            .into_iter()
+            .enumerate()
+            .map(|(i, cube)| (cube, 0.25 + 0.1 * i as f32))
+            .collect();
+
+        let full = create_elevated_hex_chunk_lod(&tiles, TILE_SIZE, 0);
+        let coarse = create_elevated_hex_chunk_lod(&tiles, TILE_SIZE, 1);
+
+        assert!((bounds(&full).1.y - 0.85).abs() < 1e-5);
+        assert!((bounds(&coarse).1.y - 0.85).abs() < 1e-5);
+        // The skirt around the chunk reaches down to the ground.
+        assert!(bounds(&coarse).0.y.abs() < 1e-5);
+    }
+}
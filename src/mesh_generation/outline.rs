@@ -0,0 +1,267 @@
+use std::collections::{BTreeMap, HashSet};
+
+use bevy::prelude::{Vec2, Vec3};
+
+use crate::coordinates::{Axial, Cube, Direction};
+
+use super::hex::{assert_finite, get_hex_point};
+use super::submesh::{SubMesh, Triangle, Vertex, INDICES_IN_RANGE};
+
+/// Flat ribbon of the given `width` along a closed loop in the XZ plane, facing up. The loop has
+/// to run in the same direction as the hex corners, the ribbon then lies on the inside of it.
+fn create_ribbon(points: &[Vec3], width: f32) -> SubMesh {
+    let count = points.len();
+    let inward = |from: Vec3, to: Vec3| (to - from).cross(Vec3::Y).normalize();
+
+    let mut vertices = Vec::with_capacity(2 * (count + 1));
+    let mut length = 0.0;
+
+    // The first point is repeated at the end so the texture coordinates can run continuously
+    // along the loop.
+    for i in 0..=count {
+        let point = points[i % count];
+        let previous = points[(i + count - 1) % count];
+        let next = points[(i + 1) % count];
+
+        if i > 0 {
+            length += point.distance(previous);
+        }
+
+        let to_previous = inward(previous, point);
+        let to_next = inward(point, next);
+        let miter = (to_previous + to_next).normalize();
+        let inner = point + width / miter.dot(to_next) * miter;
+
+        vertices.push(Vertex::new(point, Vec3::Y, Vec2::new(length, 0.0)));
+        vertices.push(Vertex::new(inner, Vec3::Y, Vec2::new(length, 1.0)));
+    }
+
+    let triangles = (0..count as u32)
+        .flat_map(|i| {
+            let (outer, inner) = (2 * i, 2 * i + 1);
+            let (next_outer, next_inner) = (outer + 2, inner + 2);
+            [
+                Triangle::new(outer, inner, next_outer),
+                Triangle::new(next_outer, inner, next_inner),
+            ]
+        })
+        .collect();
+
+    SubMesh::new(vertices, triangles).expect(INDICES_IN_RANGE)
+}
+
+/// Outline of a single hex as a ribbon of `width` on the inside of its border. Panics if `size`
+/// or `width` is not finite.
+pub fn create_hex_outline(size: f32, width: f32) -> SubMesh {
+    assert_finite("size", size);
+    assert_finite("width", width);
+
+    let corners: Vec<_> = (0..6).map(|n| get_hex_point(n, size)).collect();
+    create_ribbon(&corners, width)
+}
+
+/// Outlines of all hexes, each placed at the world position of its coordinate and raised by
+/// `height` so it can be drawn on top of the tiles. Panics if a dimension is not finite.
+pub fn create_grid_outline<I>(cubes: I, size: f32, width: f32, height: f32) -> SubMesh
+where
+    I: IntoIterator<Item = Cube>,
+{
+    assert_finite("height", height);
+
+    cubes
+        .into_iter()
+        .map(|cube| {
+            create_hex_outline(size, width)
+                .translate(Vec3::from(Axial::from(cube)) + height * Vec3::Y)
+                .expect("tile positions and height are finite")
+        })
+        .fold(SubMesh::empty(), SubMesh::merge)
+}
+
+/// Closed loops along all edges between hexes in `cubes` and hexes outside of it, as world
+/// positions of the hex corners. Outer borders and the borders of holes run in opposite
+/// directions, so the hexes of the set are always on the same side of a loop.
+pub fn border_loops(cubes: &HashSet<Cube>) -> Vec<Vec<Vec3>> {
+    // A corner is shared by three hexes and lies at the centre of their positions. The sum of
+    // their coordinates identifies it exactly, no matter which of the hexes it is reached from.
+    let corner = |cube: Cube, n: u8| {
+        let previous = cube + Direction::from_side_index((n + 5) % 6);
+        let next = cube + Direction::from_side_index(n % 6);
+        let key = cube + previous + next;
+        let key = (key.q, key.r);
+        let position = [cube, previous, next]
+            .into_iter()
+            .map(|c| Vec3::from(Axial::from(c)))
+            .fold(Vec3::ZERO, |sum, p| sum + p)
+            / 3.0;
+        (key, position)
+    };
+
+    // Only three hexes meet at a corner, so there is never more than one border edge leaving
+    // it and the loops can be followed without ambiguity. Loops start at their smallest corner,
+    // so the result does not depend on the order of `cubes`.
+    let mut edges = BTreeMap::new();
+    for cube in cubes {
+        for side in 0..6u8 {
+            if cubes.contains(&(*cube + Direction::from_side_index(side))) {
+                continue;
+            }
+            let (start, position) = corner(*cube, side);
+            let (end, _) = corner(*cube, side + 1);
+            edges.insert(start, (end, position));
+        }
+    }
+
+    let mut loops = vec![];
+    while let Some(&start) = edges.keys().next() {
+        let mut points = vec![];
+        let mut current = start;
+        while let Some((next, position)) = edges.remove(&current) {
+            points.push(position);
+            current = next;
+        }
+        loops.push(points);
+    }
+
+    loops
+}
+
+/// Border of the area covered by `cubes`, as ribbons of `width` just inside of it and raised by
+/// `height`. Holes in the area get their own border. Panics if `width` or `height` is not
+/// finite.
+pub fn create_border(cubes: &HashSet<Cube>, width: f32, height: f32) -> SubMesh {
+    assert_finite("width", width);
+    assert_finite("height", height);
+
+    border_loops(cubes)
+        .into_iter()
+        .map(|points| {
+            create_ribbon(&points, width)
+                .translate(height * Vec3::Y)
+                .expect("height is finite")
+        })
+        .fold(SubMesh::empty(), SubMesh::merge)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn area(points: &[Vec3]) -> f32 {
+        // Shoelace formula in the XZ plane, positive for loops running like the hex corners.
+        (0..points.len())
+            .map(|i| {
+                let (a, b) = (points[i], points[(i + 1) % points.len()]);
+                a.x * b.z - b.x * a.z
+            })
+            .sum::<f32>()
+            / 2.0
+    }
+
+    fn faces_up(mesh: &SubMesh) -> bool {
+        mesh.triangles().iter().all(|triangle| {
+            let [a, b, c] = triangle
+                .indices
+                .map(|i| mesh.vertices()[i as usize].position);
+            (b - a).cross(c - a).y > 0.0
+        })
+    }
+
+    #[test]
+    fn hex_outline_is_a_closed_ribbon() {
+        let mesh = create_hex_outline(0.5, 0.05);
+
+        assert_eq!(mesh.triangles().len(), 12);
+        assert!(mesh.validate().is_ok());
+        assert!(faces_up(&mesh));
+
+        for vertex in mesh.vertices() {
+            let distance = Vec2::new(vertex.position.x, vertex.position.z).length();
+            assert!(distance <= 0.5 + 1e-6);
+            assert!(distance >= 0.5 - 0.05 / (30f32).to_radians().cos() - 1e-6);
+        }
+    }
+
+    #[test]
+    fn grid_outline_contains_all_hexes() {
+        let cubes: Vec<_> = std::iter::once(Cube::origin())
+            .chain(Cube::origin().neighbours())
+            .collect();
+
+        let mesh = create_grid_outline(cubes, 0.5, 0.02, 0.3);
+
+        assert_eq!(mesh.triangles().len(), 7 * 12);
+        assert!(mesh
+            .vertices()
+            .iter()
+            .all(|v| (v.position.y - 0.3).abs() < 1e-6));
+    }
+
+    #[test]
+    fn single_hex_border_follows_its_corners() {
+        let loops = border_loops(&HashSet::from([Cube::origin()]));
+
+        assert_eq!(loops.len(), 1);
+        assert_eq!(loops[0].len(), 6);
+        for point in &loops[0] {
+            assert!((0..6).any(|n| point.abs_diff_eq(get_hex_point(n, 0.5), 1e-5)));
+        }
+        assert!(area(&loops[0]) > 0.0);
+    }
+
+    #[test]
+    fn border_skips_inner_edges() {
+        let cubes: HashSet<_> = std::iter::once(Cube::origin())
+            .chain(Cube::origin().neighbours())
+            .collect();
+
+        let loops = border_loops(&cubes);
+
+        assert_eq!(loops.len(), 1);
+        assert_eq!(loops[0].len(), 18);
+    }
+
+    #[test]
+    fn ring_has_outer_border_and_hole() {
+        let cubes: HashSet<_> = Cube::origin().neighbours().collect();
+
+        let mut loops = border_loops(&cubes);
+        loops.sort_by_key(|points| points.len());
+
+        assert_eq!(loops.len(), 2);
+        assert_eq!(loops[0].len(), 6);
+        assert_eq!(loops[1].len(), 18);
+        // The hole runs the other way round than the outer border.
+        assert!(area(&loops[0]) < 0.0);
+        assert!(area(&loops[1]) > 0.0);
+
+        let mesh = create_border(&cubes, 0.05, 0.1);
+        assert_eq!(mesh.triangles().len(), 2 * (6 + 18));
+        assert!(faces_up(&mesh));
+    }
+
+    #[test]
+    fn loops_do_not_depend_on_set_order() {
+        let cubes: Vec<_> = Cube::origin()
+            .range(3)
+            .filter(|c| *c != Cube::origin())
+            .collect();
+
+        let expected = border_loops(&cubes.iter().copied().collect());
+        for _ in 0..10 {
+            let shuffled: HashSet<_> = cubes.iter().rev().copied().collect();
+            assert_eq!(border_loops(&shuffled), expected);
+        }
+    }
+
+    #[test]
+    fn separate_areas_give_separate_loops() {
+        let a = Cube::origin();
+        let b = a + Direction::from_side_index(0) + Direction::from_side_index(1);
+
+        let loops = border_loops(&HashSet::from([a, b]));
+
+        assert_eq!(loops.len(), 2);
+        assert!(loops.iter().all(|points| points.len() == 6));
+    }
+}
@@ -1,12 +1,82 @@
-use bevy::prelude::{Mesh, Quat, Vec2, Vec3};
-use bevy::render::mesh::Indices;
-use bevy::render::render_resource::PrimitiveTopology;
+use std::collections::HashMap;
+
+use bevy::math::{Affine3A, IVec3, Mat3, Mat4};
+use bevy::prelude::{Mesh, Quat, Vec2, Vec3, Vec4};
+use bevy::render::mesh::{Indices, MeshVertexAttribute};
+use bevy::render::render_resource::{PrimitiveTopology, VertexFormat};
+use thiserror::Error;
+
+/// Generators only emit triangles between the vertices they built themselves, so `SubMesh::new`
+/// cannot fail for them.
+pub(super) const INDICES_IN_RANGE: &str = "generated triangles only reference generated vertices";
+
+pub const ATTRIBUTE_UV_1: MeshVertexAttribute =
+    MeshVertexAttribute::new("Vertex_Uv_1", 988540917, VertexFormat::Float32x2);
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum MeshError {
+    #[error("Triangle {triangle} references vertex {index} which does not exist")]
+    IndexOutOfRange { triangle: usize, index: u32 },
+    #[error("Translation contains NaN")]
+    NanTranslation,
+    #[error("Rotation contains NaN")]
+    NanRotation,
+    #[error("Rotation has zero length")]
+    ZeroRotation,
+    #[error("Transformation contains NaN or infinite values")]
+    NonFiniteTransform,
+    #[error("Transformation is not affine")]
+    NonAffineTransform,
+    #[error("Transformation collapses the mesh, its determinant is zero")]
+    SingularTransform,
+    #[error("Mirror plane normal has zero length or contains NaN")]
+    InvalidMirrorPlane,
+    #[error("Triangle {triangle} has no area")]
+    DegenerateTriangle { triangle: usize },
+    #[error("Normal of vertex {vertex} is not of unit length")]
+    NonUnitNormal { vertex: usize },
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Shading {
+    /// Every triangle gets its own vertices with the face normal.
+    Flat,
+    /// Vertex normals average the normals of all faces sharing the position, weighted by the
+    /// angle of the face at that corner. Faces whose normals differ by more than `crease_angle`
+    /// (radians) don't contribute to each other, which keeps hard edges hard.
+    Smooth { crease_angle: f32 },
+}
+
+/// Positions closer than this are treated as the same point when smoothing normals.
+const SMOOTHING_EPSILON: f32 = 1e-5;
 
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Vertex {
     pub(super) position: Vec3,
     pub(super) normal: Vec3,
     pub(super) uv: Vec2,
+    pub(super) uv_1: Option<Vec2>,
+    /// xyz is the tangent, w the handedness of the bitangent, as computed by MikkTSpace.
+    pub(super) tangent: Option<Vec4>,
+}
+
+impl Vertex {
+    pub fn new(position: Vec3, normal: Vec3, uv: Vec2) -> Self {
+        Vertex {
+            position,
+            normal,
+            uv,
+            uv_1: None,
+            tangent: None,
+        }
+    }
+
+    pub fn with_uv_1(self, uv_1: Vec2) -> Self {
+        Vertex {
+            uv_1: Some(uv_1),
+            ..self
+        }
+    }
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
@@ -30,19 +100,14 @@ impl From<&[u32; 3]> for Triangle {
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct SubMesh {
-    vertices: Vec<Vertex>,
-    triangles: Vec<Triangle>,
+    pub(super) vertices: Vec<Vertex>,
+    pub(super) triangles: Vec<Triangle>,
 }
 
 impl SubMesh {
-    pub fn new(vertices: Vec<Vertex>, triangles: Vec<Triangle>) -> Result<SubMesh, ()> {
-        let indices_in_range = triangles
-            .iter()
-            .flat_map(|t| t.indices.iter())
-            .all(|idx| (*idx as usize) < vertices.len());
-
-        if !indices_in_range {
-            return Err(());
+    pub fn new(vertices: Vec<Vertex>, triangles: Vec<Triangle>) -> Result<SubMesh, MeshError> {
+        if let Some(error) = SubMesh::index_errors(vertices.len(), &triangles).next() {
+            return Err(error);
         }
 
         Ok(SubMesh {
@@ -51,6 +116,73 @@ impl SubMesh {
         })
     }
 
+    /// Mesh without vertices or triangles, the starting point for merging meshes together.
+    pub fn empty() -> SubMesh {
+        SubMesh {
+            vertices: vec![],
+            triangles: vec![],
+        }
+    }
+
+    pub(super) fn index_errors(
+        vertex_count: usize,
+        triangles: &[Triangle],
+    ) -> impl Iterator<Item = MeshError> + '_ {
+        triangles.iter().enumerate().flat_map(move |(triangle, t)| {
+            t.indices
+                .iter()
+                .filter(move |index| **index as usize >= vertex_count)
+                .map(move |index| MeshError::IndexOutOfRange {
+                    triangle,
+                    index: *index,
+                })
+        })
+    }
+
+    /// Checks the whole mesh and reports every problem found instead of stopping at the first.
+    pub fn validate(&self) -> Result<(), Vec<MeshError>> {
+        let mut errors: Vec<_> =
+            SubMesh::index_errors(self.vertices.len(), &self.triangles).collect();
+
+        let degenerate = self
+            .triangles
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| {
+                t.indices
+                    .iter()
+                    .all(|i| (*i as usize) < self.vertices.len())
+            })
+            .filter(|(_, t)| {
+                let [a, b, c] = t.indices.map(|i| self.vertices[i as usize].position);
+                (b - a).cross(c - a).length() <= f32::EPSILON
+            })
+            .map(|(triangle, _)| MeshError::DegenerateTriangle { triangle });
+        errors.extend(degenerate);
+
+        let non_unit_normals = self
+            .vertices
+            .iter()
+            .enumerate()
+            .filter(|(_, v)| !v.normal.is_normalized())
+            .map(|(vertex, _)| MeshError::NonUnitNormal { vertex });
+        errors.extend(non_unit_normals);
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    pub fn vertices(&self) -> &[Vertex] {
+        &self.vertices
+    }
+
+    pub fn triangles(&self) -> &[Triangle] {
+        &self.triangles
+    }
+
     #[inline(always)]
     fn modify_vertices<F>(vertices: &mut Vec<Vertex>, fun: F)
     where
@@ -61,13 +193,13 @@ impl SubMesh {
         }
     }
 
-    pub fn rotate(mut self, rotation: Quat) -> Result<SubMesh, ()> {
+    pub fn rotate(mut self, rotation: Quat) -> Result<SubMesh, MeshError> {
         if rotation.is_nan() {
-            return Err(());
+            return Err(MeshError::NanRotation);
         }
 
         if rotation.length_squared() < f32::EPSILON {
-            return Err(());
+            return Err(MeshError::ZeroRotation);
         }
 
         let rotation = rotation.normalize();
@@ -75,26 +207,99 @@ impl SubMesh {
         SubMesh::modify_vertices(&mut self.vertices, |v| Vertex {
             position: rotation * v.position,
             normal: rotation * v.normal,
-            uv: v.uv,
+            tangent: v.tangent.map(|t| (rotation * t.truncate()).extend(t.w)),
+            ..*v
         });
 
         Ok(self)
     }
 
-    pub fn translate(mut self, translation: Vec3) -> Result<SubMesh, ()> {
+    pub fn translate(mut self, translation: Vec3) -> Result<SubMesh, MeshError> {
         if translation.is_nan() {
-            return Err(());
+            return Err(MeshError::NanTranslation);
         }
 
         SubMesh::modify_vertices(&mut self.vertices, |v| Vertex {
             position: translation + v.position,
-            normal: v.normal,
-            uv: v.uv,
+            ..*v
         });
 
         Ok(self)
     }
 
+    /// Applies an affine transformation. Normals are transformed with the inverse transpose so
+    /// they stay perpendicular to the surface. Transformations that flip handedness (negative
+    /// determinant) also flip the winding of the triangles, so faces keep pointing outwards.
+    pub fn transform(mut self, transform: Affine3A) -> Result<SubMesh, MeshError> {
+        if !transform.is_finite() {
+            return Err(MeshError::NonFiniteTransform);
+        }
+
+        let linear = Mat3::from(transform.matrix3);
+        let determinant = linear.determinant();
+        // Relative to the largest determinant columns of these lengths can have, so small but
+        // valid scales are not mistaken for a collapse.
+        let volume = linear.x_axis.length() * linear.y_axis.length() * linear.z_axis.length();
+        if !determinant.is_finite() || determinant.abs() <= f32::EPSILON * volume {
+            return Err(MeshError::SingularTransform);
+        }
+
+        let normal_matrix = linear.inverse().transpose();
+        let handedness = determinant.signum();
+
+        SubMesh::modify_vertices(&mut self.vertices, |v| Vertex {
+            position: transform.transform_point3(v.position),
+            normal: (normal_matrix * v.normal).normalize_or_zero(),
+            tangent: v.tangent.map(|t| {
+                (linear * t.truncate())
+                    .normalize_or_zero()
+                    .extend(handedness * t.w)
+            }),
+            ..*v
+        });
+
+        if determinant < 0.0 {
+            for triangle in &mut self.triangles {
+                triangle.indices.swap(1, 2);
+            }
+        }
+
+        Ok(self)
+    }
+
+    pub fn transform_mat4(self, transform: Mat4) -> Result<SubMesh, MeshError> {
+        if transform.row(3) != Vec4::W {
+            return Err(MeshError::NonAffineTransform);
+        }
+
+        self.transform(Affine3A::from_mat4(transform))
+    }
+
+    pub fn scale(self, scale: Vec3) -> Result<SubMesh, MeshError> {
+        self.transform(Affine3A::from_scale(scale))
+    }
+
+    pub fn scale_uniform(self, scale: f32) -> Result<SubMesh, MeshError> {
+        self.scale(Vec3::splat(scale))
+    }
+
+    /// Mirrors the mesh at the plane through the origin with the given normal.
+    pub fn mirror(self, plane_normal: Vec3) -> Result<SubMesh, MeshError> {
+        let plane_normal = plane_normal
+            .try_normalize()
+            .ok_or(MeshError::InvalidMirrorPlane)?;
+
+        let reflection = Mat3::IDENTITY
+            - 2.0
+                * Mat3::from_cols(
+                    plane_normal * plane_normal.x,
+                    plane_normal * plane_normal.y,
+                    plane_normal * plane_normal.z,
+                );
+
+        self.transform(Affine3A::from_mat3(reflection))
+    }
+
     pub fn merge(self, mut other: SubMesh) -> SubMesh {
         let mut vertices = self.vertices;
         let mut triangles = self.triangles;
@@ -118,10 +323,255 @@ impl SubMesh {
             triangles,
         }
     }
+
+    /// Computes per-vertex tangents with MikkTSpace, the same way `Mesh::generate_tangents` does
+    /// in later Bevy versions, so normal maps baked by other tools shade as intended. Vertices
+    /// that are not part of any triangle get an arbitrary tangent perpendicular to their normal.
+    pub fn generate_tangents(mut self) -> SubMesh {
+        let mut geometry = TangentGeometry {
+            vertices: &self.vertices,
+            triangles: &self.triangles,
+            tangents: vec![None; self.vertices.len()],
+        };
+        bevy_mikktspace::generate_tangents(&mut geometry);
+
+        let tangents = geometry.tangents;
+        for (vertex, tangent) in self.vertices.iter_mut().zip(tangents) {
+            let tangent =
+                tangent.unwrap_or_else(|| vertex.normal.any_orthonormal_vector().extend(1.0));
+            vertex.tangent = Some(tangent);
+        }
+
+        self
+    }
+
+    /// Generates tangents for the vertices that have none if any other vertex has one, which
+    /// happens when a mesh with tangents is merged with one without. Existing tangents are kept.
+    fn fill_missing_tangents(mut self) -> SubMesh {
+        let has_tangents = self.vertices.iter().any(|v| v.tangent.is_some());
+        if !has_tangents || self.vertices.iter().all(|v| v.tangent.is_some()) {
+            return self;
+        }
+
+        let generated = self.clone().generate_tangents();
+        for (vertex, generated) in self.vertices.iter_mut().zip(generated.vertices) {
+            vertex.tangent = vertex.tangent.or(generated.tangent);
+        }
+
+        self
+    }
+
+    /// Maps every vertex to the first vertex within `epsilon` for which `same` holds.
+    pub(super) fn find_representatives<F>(vertices: &[Vertex], epsilon: f32, same: F) -> Vec<usize>
+    where
+        F: Fn(&Vertex, &Vertex) -> bool,
+    {
+        let epsilon = epsilon.max(f32::EPSILON);
+        let cell_of = |p: Vec3| (p / epsilon).floor().as_ivec3();
+
+        let mut cells: HashMap<IVec3, Vec<usize>> = HashMap::new();
+        let mut representatives = Vec::with_capacity(vertices.len());
+
+        for (index, vertex) in vertices.iter().enumerate() {
+            let cell = cell_of(vertex.position);
+            let mut found: Option<usize> = None;
+
+            for x in -1..=1 {
+                for y in -1..=1 {
+                    for z in -1..=1 {
+                        let candidates = cells.get(&(cell + IVec3::new(x, y, z)));
+                        for &candidate in candidates.into_iter().flatten() {
+                            let other = &vertices[candidate];
+                            if other.position.distance(vertex.position) <= epsilon
+                                && same(other, vertex)
+                            {
+                                found = Some(found.map_or(candidate, |f| f.min(candidate)));
+                            }
+                        }
+                    }
+                }
+            }
+
+            match found {
+                Some(representative) => representatives.push(representative),
+                None => {
+                    cells.entry(cell).or_default().push(index);
+                    representatives.push(index);
+                }
+            }
+        }
+
+        representatives
+    }
+
+    /// Merges vertices that are closer than `epsilon` and also agree in normal and texture
+    /// coordinates. Triangles that collapse because of the merge are removed.
+    pub fn weld(self, epsilon: f32) -> SubMesh {
+        let representatives = SubMesh::find_representatives(&self.vertices, epsilon, |a, b| {
+            a.normal.abs_diff_eq(b.normal, epsilon)
+                && a.uv.abs_diff_eq(b.uv, epsilon)
+                && a.uv_1 == b.uv_1
+        });
+
+        let mut new_indices = vec![0u32; self.vertices.len()];
+        let mut vertices = vec![];
+        for (index, representative) in representatives.iter().enumerate() {
+            if index == *representative {
+                new_indices[index] = vertices.len() as u32;
+                vertices.push(self.vertices[index]);
+            } else {
+                new_indices[index] = new_indices[*representative];
+            }
+        }
+
+        let triangles = self
+            .triangles
+            .iter()
+            .map(|t| Triangle {
+                indices: t.indices.map(|i| new_indices[i as usize]),
+            })
+            .filter(|t| {
+                let [a, b, c] = t.indices;
+                a != b && b != c && a != c
+            })
+            .collect();
+
+        SubMesh {
+            vertices,
+            triangles,
+        }
+    }
+
+    /// Replaces all normals. Tangents are dropped because they depend on the normals, call
+    /// `generate_tangents` again if they are needed.
+    pub fn recompute_normals(self, shading: Shading) -> SubMesh {
+        let face_normals: Vec<Vec3> = self
+            .triangles
+            .iter()
+            .map(|t| {
+                let [a, b, c] = t.indices.map(|i| self.vertices[i as usize].position);
+                (b - a).cross(c - a).normalize_or_zero()
+            })
+            .collect();
+
+        let mut vertices = vec![];
+        let mut triangles = Vec::with_capacity(self.triangles.len());
+
+        match shading {
+            Shading::Flat => {
+                for (triangle, normal) in self.triangles.iter().zip(&face_normals) {
+                    let offset = vertices.len() as u32;
+                    for i in triangle.indices {
+                        vertices.push(Vertex {
+                            normal: *normal,
+                            tangent: None,
+                            ..self.vertices[i as usize]
+                        });
+                    }
+                    triangles.push(Triangle::new(offset, offset + 1, offset + 2));
+                }
+            }
+            Shading::Smooth { crease_angle } => {
+                let representatives =
+                    SubMesh::find_representatives(&self.vertices, SMOOTHING_EPSILON, |_, _| true);
+
+                // All triangle corners grouped by position, with the angle of the triangle there.
+                let mut corners: HashMap<usize, Vec<(usize, f32)>> = HashMap::new();
+                for (t, triangle) in self.triangles.iter().enumerate() {
+                    let positions = triangle.indices.map(|i| self.vertices[i as usize].position);
+                    for k in 0..3 {
+                        let corner = positions[k];
+                        let angle = (positions[(k + 1) % 3] - corner)
+                            .angle_between(positions[(k + 2) % 3] - corner);
+                        let angle = if angle.is_nan() { 0.0 } else { angle };
+                        let key = representatives[triangle.indices[k] as usize];
+                        corners.entry(key).or_default().push((t, angle));
+                    }
+                }
+
+                let min_cos = crease_angle.cos();
+                let mut lookup = HashMap::new();
+
+                for (t, triangle) in self.triangles.iter().enumerate() {
+                    let face_normal = face_normals[t];
+                    let indices = triangle.indices.map(|i| {
+                        let key = representatives[i as usize];
+                        let normal = corners[&key]
+                            .iter()
+                            .filter(|(other, _)| face_normals[*other].dot(face_normal) >= min_cos)
+                            .map(|(other, angle)| *angle * face_normals[*other])
+                            .fold(Vec3::ZERO, |sum, n| sum + n)
+                            .try_normalize()
+                            .unwrap_or(face_normal);
+
+                        *lookup
+                            .entry((i, normal.to_array().map(f32::to_bits)))
+                            .or_insert_with(|| {
+                                vertices.push(Vertex {
+                                    normal,
+                                    tangent: None,
+                                    ..self.vertices[i as usize]
+                                });
+                                vertices.len() as u32 - 1
+                            })
+                    });
+                    triangles.push(Triangle { indices });
+                }
+            }
+        }
+
+        SubMesh {
+            vertices,
+            triangles,
+        }
+    }
+}
+
+/// Triangles of a `SubMesh` as seen by MikkTSpace. Corners that share a vertex get the tangent of
+/// the last corner written, like Bevy does for indexed meshes.
+struct TangentGeometry<'a> {
+    vertices: &'a [Vertex],
+    triangles: &'a [Triangle],
+    tangents: Vec<Option<Vec4>>,
+}
+
+impl TangentGeometry<'_> {
+    fn vertex(&self, face: usize, vert: usize) -> &Vertex {
+        &self.vertices[self.triangles[face].indices[vert] as usize]
+    }
+}
+
+impl bevy_mikktspace::Geometry for TangentGeometry<'_> {
+    fn num_faces(&self) -> usize {
+        self.triangles.len()
+    }
+
+    fn num_vertices_of_face(&self, _face: usize) -> usize {
+        3
+    }
+
+    fn position(&self, face: usize, vert: usize) -> [f32; 3] {
+        self.vertex(face, vert).position.to_array()
+    }
+
+    fn normal(&self, face: usize, vert: usize) -> [f32; 3] {
+        self.vertex(face, vert).normal.to_array()
+    }
+
+    fn tex_coord(&self, face: usize, vert: usize) -> [f32; 2] {
+        self.vertex(face, vert).uv.to_array()
+    }
+
+    fn set_tangent_encoded(&mut self, tangent: [f32; 4], face: usize, vert: usize) {
+        let index = self.triangles[face].indices[vert] as usize;
+        self.tangents[index] = Some(Vec4::from_array(tangent));
+    }
 }
 
 impl From<SubMesh> for Mesh {
     fn from(x: SubMesh) -> Self {
+        let x = x.fill_missing_tangents();
+
         let (vertices, (normals, uvs)): (Vec<_>, (Vec<_>, Vec<_>)) = x
             .vertices
             .iter()
@@ -141,10 +591,30 @@ impl From<SubMesh> for Mesh {
                 .collect(),
         );
 
+        let tangents: Option<Vec<_>> = x
+            .vertices
+            .iter()
+            .map(|v| v.tangent.map(|t| [t.x, t.y, t.z, t.w]))
+            .collect();
+        // Parts without a second UV set get zeros, so merging them doesn't lose the attribute.
+        let uvs_1: Option<Vec<_>> = x.vertices.iter().any(|v| v.uv_1.is_some()).then(|| {
+            x.vertices
+                .iter()
+                .map(|v| v.uv_1.unwrap_or(Vec2::ZERO))
+                .map(|uv| [uv.x, uv.y])
+                .collect()
+        });
+
         let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
         mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, vertices);
         mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
         mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+        if let Some(tangents) = tangents {
+            mesh.insert_attribute(Mesh::ATTRIBUTE_TANGENT, tangents);
+        }
+        if let Some(uvs_1) = uvs_1 {
+            mesh.insert_attribute(ATTRIBUTE_UV_1, uvs_1);
+        }
         mesh.set_indices(Some(indices));
         mesh
     }
@@ -152,39 +622,474 @@ impl From<SubMesh> for Mesh {
 
 #[cfg(test)]
 mod tests {
+    use bevy::math::Vec3A;
+    use bevy::render::mesh::VertexAttributeValues;
+
     use super::*;
     #[test]
     fn emtpy_is_ok() {
         let empty = SubMesh::new(vec![], vec![]);
 
-        assert_ne!(empty, Err(()));
+        assert!(empty.is_ok());
     }
     #[test]
     fn invalid_triangle_returns_err() {
         let invalid = SubMesh::new(vec![], vec![Triangle { indices: [0, 1, 2] }]);
 
-        assert_eq!(invalid, Err(()));
+        assert_eq!(
+            invalid,
+            Err(MeshError::IndexOutOfRange {
+                triangle: 0,
+                index: 0
+            })
+        );
     }
 
     #[test]
     fn simple_triangle_returns_ok() {
-        let v1 = Vertex {
-            position: Vec3::NEG_X,
-            normal: Vec3::Z,
-            uv: Vec2::ZERO,
-        };
-        let v2 = Vertex {
-            position: Vec3::X,
-            normal: Vec3::Z,
-            uv: Vec2::X,
-        };
-        let v3 = Vertex {
-            position: Vec3::Y,
-            normal: Vec3::Z,
-            uv: Vec2::ONE,
-        };
+        let v1 = Vertex::new(Vec3::NEG_X, Vec3::Z, Vec2::ZERO);
+        let v2 = Vertex::new(Vec3::X, Vec3::Z, Vec2::X);
+        let v3 = Vertex::new(Vec3::Y, Vec3::Z, Vec2::ONE);
         let triangle = SubMesh::new(vec![v1, v2, v3], vec![Triangle { indices: [0, 1, 2] }]);
 
-        assert_ne!(triangle, Err(()));
+        assert!(triangle.is_ok());
+    }
+
+    #[test]
+    fn invalid_transformations_return_err() {
+        let empty = || SubMesh::new(vec![], vec![]).unwrap();
+
+        assert_eq!(
+            empty().translate(Vec3::new(0.0, f32::NAN, 0.0)),
+            Err(MeshError::NanTranslation)
+        );
+        assert_eq!(
+            empty().rotate(Quat::from_xyzw(f32::NAN, 0.0, 0.0, 1.0)),
+            Err(MeshError::NanRotation)
+        );
+        assert_eq!(
+            empty().rotate(Quat::from_xyzw(0.0, 0.0, 0.0, 0.0)),
+            Err(MeshError::ZeroRotation)
+        );
+    }
+
+    fn slanted_triangle() -> SubMesh {
+        let normal = Vec3::new(1.0, 1.0, 0.0).normalize();
+        let v1 = Vertex::new(Vec3::X, normal, Vec2::ZERO);
+        let v2 = Vertex::new(Vec3::Y, normal, Vec2::X);
+        let v3 = Vertex::new(Vec3::X + Vec3::Z, normal, Vec2::Y);
+        SubMesh::new(vec![v1, v2, v3], vec![Triangle::new(0, 1, 2)])
+            .unwrap()
+            .generate_tangents()
+    }
+
+    fn assert_normals_match_faces(mesh: &SubMesh) {
+        for triangle in &mesh.triangles {
+            let [a, b, c] = triangle.indices.map(|i| mesh.vertices[i as usize]);
+            let face_normal = (b.position - a.position)
+                .cross(c.position - a.position)
+                .normalize();
+
+            for vertex in [a, b, c] {
+                assert!(vertex.normal.abs_diff_eq(face_normal, 1e-5));
+            }
+        }
+    }
+
+    #[test]
+    fn non_uniform_scale_keeps_normals_perpendicular() {
+        let scaled = slanted_triangle().scale(Vec3::new(3.0, 0.5, 2.0)).unwrap();
+
+        assert_eq!(scaled.vertices[0].position, Vec3::new(3.0, 0.0, 0.0));
+        assert_normals_match_faces(&scaled);
+    }
+
+    #[test]
+    fn uniform_scale_keeps_normals() {
+        let original = slanted_triangle();
+        let scaled = original.clone().scale_uniform(2.0).unwrap();
+
+        for (a, b) in original.vertices.iter().zip(&scaled.vertices) {
+            assert_eq!(a.position * 2.0, b.position);
+            assert!(a.normal.abs_diff_eq(b.normal, 1e-6));
+        }
+    }
+
+    #[test]
+    fn mirror_flips_winding() {
+        let original = slanted_triangle();
+        let mirrored = original.clone().mirror(Vec3::X).unwrap();
+
+        assert_eq!(mirrored.triangles[0], Triangle::new(0, 2, 1));
+        assert_eq!(mirrored.vertices[0].position, Vec3::NEG_X);
+        assert_normals_match_faces(&mirrored);
+        assert_eq!(
+            mirrored.vertices[0].tangent.unwrap().w,
+            -original.vertices[0].tangent.unwrap().w
+        );
+    }
+
+    #[test]
+    fn transform_matches_rotate_and_translate() {
+        let rotation = Quat::from_rotation_y(1.0);
+        let translation = Vec3::new(1.0, 2.0, 3.0);
+
+        let expected = slanted_triangle()
+            .rotate(rotation)
+            .unwrap()
+            .translate(translation)
+            .unwrap();
+        let transformed = slanted_triangle()
+            .transform_mat4(Mat4::from_rotation_translation(rotation, translation))
+            .unwrap();
+
+        for (a, b) in expected.vertices.iter().zip(&transformed.vertices) {
+            assert!(a.position.abs_diff_eq(b.position, 1e-5));
+            assert!(a.normal.abs_diff_eq(b.normal, 1e-5));
+            assert!(a.tangent.unwrap().abs_diff_eq(b.tangent.unwrap(), 1e-5));
+        }
+    }
+
+    #[test]
+    fn small_scales_are_not_singular() {
+        let scaled = slanted_triangle().scale_uniform(0.001).unwrap();
+
+        for (a, b) in slanted_triangle().vertices.iter().zip(&scaled.vertices) {
+            assert!((0.001 * a.position).abs_diff_eq(b.position, 1e-9));
+            assert!(a.normal.abs_diff_eq(b.normal, 1e-5));
+        }
+        assert!(slanted_triangle()
+            .scale(Vec3::new(1e-4, 1e-3, 1e-4))
+            .is_ok());
+    }
+
+    #[test]
+    fn invalid_affine_transformations_return_err() {
+        assert_eq!(
+            slanted_triangle().scale(Vec3::new(1.0, 0.0, 1.0)),
+            Err(MeshError::SingularTransform)
+        );
+        assert_eq!(
+            slanted_triangle().transform(Affine3A::from_cols(
+                Vec3A::X,
+                Vec3A::new(1.0, 1e-9, 0.0),
+                Vec3A::Z,
+                Vec3A::ZERO
+            )),
+            Err(MeshError::SingularTransform)
+        );
+        assert_eq!(
+            slanted_triangle().scale_uniform(f32::NAN),
+            Err(MeshError::NonFiniteTransform)
+        );
+        assert_eq!(
+            slanted_triangle().transform_mat4(Mat4::perspective_rh(1.0, 1.0, 0.1, 10.0)),
+            Err(MeshError::NonAffineTransform)
+        );
+        assert_eq!(
+            slanted_triangle().mirror(Vec3::ZERO),
+            Err(MeshError::InvalidMirrorPlane)
+        );
+    }
+
+    fn hex_prism() -> SubMesh {
+        crate::mesh_generation::hex::create_hex_prism(0.5, 0.25)
+    }
+
+    #[test]
+    fn flat_shading_splits_vertices() {
+        let flat = hex_prism().recompute_normals(Shading::Flat);
+
+        assert_eq!(flat.vertices.len(), 3 * flat.triangles.len());
+        assert_normals_match_faces(&flat);
+    }
+
+    #[test]
+    fn smooth_shading_keeps_creases() {
+        let prism = hex_prism();
+        let smooth = prism.clone().recompute_normals(Shading::Smooth {
+            crease_angle: 30.0_f32.to_radians(),
+        });
+
+        assert_eq!(smooth.vertices.len(), prism.vertices.len());
+        assert_normals_match_faces(&smooth);
+    }
+
+    #[test]
+    fn smooth_shading_averages_over_shared_positions() {
+        let smooth = hex_prism().recompute_normals(Shading::Smooth {
+            crease_angle: std::f32::consts::PI,
+        });
+
+        let top_centre = smooth
+            .vertices
+            .iter()
+            .find(|v| v.position.abs_diff_eq(Vec3::Y * 0.25, 1e-6))
+            .unwrap();
+        assert!(top_centre.normal.abs_diff_eq(Vec3::Y, 1e-6));
+
+        let top_corner: Vec<_> = smooth
+            .vertices
+            .iter()
+            .filter(|v| v.position.y > 0.2 && v.position.length() > 0.3)
+            .collect();
+        assert!(!top_corner.is_empty());
+        for vertex in top_corner {
+            assert!(vertex.normal.is_normalized());
+            assert!(vertex.normal.y > 0.1 && vertex.normal.y < 0.9);
+        }
+    }
+
+    #[test]
+    fn smooth_shading_of_plane_points_up() {
+        let plane = crate::mesh_generation::hex::create_subdivided_hex(0.5, 3, Vec3::ZERO, |_| 0.0)
+            .recompute_normals(Shading::Smooth { crease_angle: 1.0 });
+
+        for vertex in plane.vertices {
+            assert!(vertex.normal.abs_diff_eq(Vec3::Y, 1e-6));
+        }
+    }
+
+    #[test]
+    fn weld_merges_duplicates() {
+        let hex = crate::mesh_generation::hex::create_hex(0.5);
+        let doubled = hex
+            .clone()
+            .merge(hex.clone().translate(Vec3::Y * 1e-7).unwrap());
+
+        let welded = doubled.weld(1e-5);
+
+        assert_eq!(welded.vertices.len(), hex.vertices.len());
+        assert_eq!(welded.triangles.len(), 2 * hex.triangles.len());
+        assert_eq!(welded.triangles[..6], welded.triangles[6..]);
+    }
+
+    #[test]
+    fn weld_keeps_seams_and_drops_collapsed_triangles() {
+        let v1 = Vertex::new(Vec3::ZERO, Vec3::Z, Vec2::ZERO);
+        let v2 = Vertex::new(Vec3::X, Vec3::Z, Vec2::X);
+        let v3 = Vertex::new(Vec3::X * 1e-7, Vec3::Z, Vec2::ZERO);
+        let v4 = Vertex::new(Vec3::Y, Vec3::Z, Vec2::Y);
+        let seam = Vertex::new(Vec3::Y, Vec3::Z, Vec2::ONE);
+        let mesh = SubMesh::new(
+            vec![v1, v2, v3, v4, seam],
+            vec![
+                Triangle::new(0, 1, 3),
+                Triangle::new(0, 2, 3),
+                Triangle::new(1, 4, 0),
+            ],
+        )
+        .unwrap();
+
+        let welded = mesh.weld(1e-5);
+
+        assert_eq!(welded.vertices.len(), 4);
+        assert_eq!(
+            welded.triangles,
+            vec![Triangle::new(0, 1, 2), Triangle::new(1, 3, 0)]
+        );
+    }
+
+    #[test]
+    fn validate_reports_all_problems() {
+        let v1 = Vertex::new(Vec3::ZERO, Vec3::Z, Vec2::ZERO);
+        let v2 = Vertex::new(Vec3::X, Vec3::Z * 2.0, Vec2::ZERO);
+        let v3 = Vertex::new(Vec3::Y, Vec3::Z, Vec2::ZERO);
+        let mut mesh = SubMesh::new(
+            vec![v1, v2, v3],
+            vec![Triangle::new(0, 1, 2), Triangle::new(0, 1, 1)],
+        )
+        .unwrap();
+        mesh.triangles.push(Triangle::new(0, 5, 1));
+
+        assert_eq!(
+            mesh.validate(),
+            Err(vec![
+                MeshError::IndexOutOfRange {
+                    triangle: 2,
+                    index: 5
+                },
+                MeshError::DegenerateTriangle { triangle: 1 },
+                MeshError::NonUnitNormal { vertex: 1 },
+            ])
+        );
+    }
+
+    #[test]
+    fn valid_mesh_passes_validation() {
+        let v1 = Vertex::new(Vec3::ZERO, Vec3::Z, Vec2::ZERO);
+        let v2 = Vertex::new(Vec3::X, Vec3::Z, Vec2::ZERO);
+        let v3 = Vertex::new(Vec3::Y, Vec3::Z, Vec2::ZERO);
+        let mesh = SubMesh::new(vec![v1, v2, v3], vec![Triangle::new(0, 1, 2)]).unwrap();
+
+        assert_eq!(mesh.validate(), Ok(()));
+    }
+
+    #[test]
+    fn tangents_follow_uv_u_direction() {
+        let v1 = Vertex::new(Vec3::ZERO, Vec3::Z, Vec2::ZERO);
+        let v2 = Vertex::new(Vec3::X, Vec3::Z, Vec2::X);
+        let v3 = Vertex::new(Vec3::Y, Vec3::Z, Vec2::Y);
+        let mesh = SubMesh::new(vec![v1, v2, v3], vec![Triangle::new(0, 1, 2)])
+            .unwrap()
+            .generate_tangents();
+
+        for vertex in mesh.vertices {
+            assert_eq!(vertex.tangent, Some(Vec4::new(1.0, 0.0, 0.0, 1.0)));
+        }
+    }
+
+    #[test]
+    fn mirrored_uvs_flip_tangent_handedness() {
+        let v1 = Vertex::new(Vec3::ZERO, Vec3::Z, Vec2::ZERO);
+        let v2 = Vertex::new(Vec3::X, Vec3::Z, Vec2::X);
+        let v3 = Vertex::new(Vec3::Y, Vec3::Z, Vec2::NEG_Y);
+        let mesh = SubMesh::new(vec![v1, v2, v3], vec![Triangle::new(0, 1, 2)])
+            .unwrap()
+            .generate_tangents();
+
+        for vertex in mesh.vertices {
+            assert_eq!(vertex.tangent.unwrap().w, -1.0);
+        }
+    }
+
+    #[test]
+    fn degenerate_uvs_still_produce_perpendicular_tangents() {
+        let v1 = Vertex::new(Vec3::ZERO, Vec3::Y, Vec2::ZERO);
+        let v2 = Vertex::new(Vec3::X, Vec3::Y, Vec2::ZERO);
+        let v3 = Vertex::new(Vec3::Z, Vec3::Y, Vec2::ZERO);
+        let mesh = SubMesh::new(vec![v1, v2, v3], vec![Triangle::new(0, 2, 1)])
+            .unwrap()
+            .generate_tangents();
+
+        for vertex in mesh.vertices {
+            let tangent = vertex.tangent.unwrap().truncate();
+            assert!(tangent.is_normalized());
+            assert!(tangent.dot(Vec3::Y).abs() < 1e-6);
+        }
+    }
+
+    /// Every triangle corner as its own vertex, the layout MikkTSpace was designed for.
+    struct Unindexed {
+        corners: Vec<Vertex>,
+        tangents: Vec<[f32; 4]>,
+    }
+
+    impl bevy_mikktspace::Geometry for Unindexed {
+        fn num_faces(&self) -> usize {
+            self.corners.len() / 3
+        }
+
+        fn num_vertices_of_face(&self, _face: usize) -> usize {
+            3
+        }
+
+        fn position(&self, face: usize, vert: usize) -> [f32; 3] {
+            self.corners[3 * face + vert].position.to_array()
+        }
+
+        fn normal(&self, face: usize, vert: usize) -> [f32; 3] {
+            self.corners[3 * face + vert].normal.to_array()
+        }
+
+        fn tex_coord(&self, face: usize, vert: usize) -> [f32; 2] {
+            self.corners[3 * face + vert].uv.to_array()
+        }
+
+        fn set_tangent_encoded(&mut self, tangent: [f32; 4], face: usize, vert: usize) {
+            self.tangents[3 * face + vert] = tangent;
+        }
+    }
+
+    fn assert_tangents_match_mikktspace(mesh: SubMesh) {
+        let mesh = mesh.generate_tangents();
+
+        let corners: Vec<_> = mesh
+            .triangles
+            .iter()
+            .flat_map(|t| t.indices.map(|i| mesh.vertices[i as usize]))
+            .collect();
+        let mut reference = Unindexed {
+            tangents: vec![[0.0; 4]; corners.len()],
+            corners,
+        };
+        assert!(bevy_mikktspace::generate_tangents(&mut reference));
+
+        for (corner, expected) in reference.corners.iter().zip(&reference.tangents) {
+            let tangent = corner.tangent.unwrap();
+            assert!(
+                tangent.abs_diff_eq(Vec4::from_array(*expected), 1e-5),
+                "{tangent} != {expected:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn tangents_match_mikktspace_reference() {
+        use crate::mesh_generation::hex::{create_beveled_hex_prism, create_subdivided_hex};
+
+        assert_tangents_match_mikktspace(create_beveled_hex_prism(0.5, 0.25, 0.05, 3));
+        // Vertices shared by triangles with different slopes, where MikkTSpace weighs the
+        // triangles differently than simply summing their tangents.
+        assert_tangents_match_mikktspace(create_subdivided_hex(0.5, 4, Vec3::ZERO, |p| {
+            0.2 * (3.0 * p.x).sin() * (5.0 * p.z).cos()
+        }));
+    }
+
+    #[test]
+    fn mesh_contains_optional_attributes_only_when_present() {
+        let v1 = Vertex::new(Vec3::ZERO, Vec3::Z, Vec2::ZERO);
+        let v2 = Vertex::new(Vec3::X, Vec3::Z, Vec2::X);
+        let v3 = Vertex::new(Vec3::Y, Vec3::Z, Vec2::Y);
+        let sub_mesh = SubMesh::new(vec![v1, v2, v3], vec![Triangle::new(0, 1, 2)]).unwrap();
+
+        let mesh = Mesh::from(sub_mesh.clone());
+        assert!(mesh.attribute(Mesh::ATTRIBUTE_TANGENT).is_none());
+        assert!(mesh.attribute(ATTRIBUTE_UV_1).is_none());
+
+        let mesh = Mesh::from(sub_mesh.generate_tangents());
+        assert!(mesh.attribute(Mesh::ATTRIBUTE_TANGENT).is_some());
+    }
+
+    #[test]
+    fn merged_meshes_keep_partial_optional_attributes() {
+        let v1 = Vertex::new(Vec3::ZERO, Vec3::Z, Vec2::ZERO).with_uv_1(Vec2::ONE);
+        let v2 = Vertex::new(Vec3::X, Vec3::Z, Vec2::X).with_uv_1(Vec2::ONE);
+        let v3 = Vertex::new(Vec3::Y, Vec3::Z, Vec2::Y).with_uv_1(Vec2::ONE);
+        let with_attributes = SubMesh::new(vec![v1, v2, v3], vec![Triangle::new(0, 1, 2)])
+            .unwrap()
+            .generate_tangents();
+        let mut without_attributes = slanted_triangle();
+        for vertex in &mut without_attributes.vertices {
+            vertex.tangent = None;
+        }
+
+        let merged = with_attributes.clone().merge(without_attributes);
+        let expected_tangents = with_attributes
+            .vertices
+            .iter()
+            .chain(&slanted_triangle().vertices)
+            .map(|v| v.tangent.unwrap().to_array())
+            .collect::<Vec<_>>();
+        let mesh = Mesh::from(merged);
+
+        match mesh.attribute(Mesh::ATTRIBUTE_TANGENT) {
+            Some(VertexAttributeValues::Float32x4(tangents)) => {
+                assert_eq!(tangents.len(), 6);
+                for (tangent, expected) in tangents.iter().zip(&expected_tangents) {
+                    assert!(
+                        Vec4::from_array(*tangent).abs_diff_eq(Vec4::from_array(*expected), 1e-5)
+                    );
+                }
+            }
+            other => panic!("unexpected tangents {other:?}"),
+        }
+        match mesh.attribute(ATTRIBUTE_UV_1) {
+            Some(VertexAttributeValues::Float32x2(uvs)) => {
+                assert_eq!(uvs[..3], [[1.0, 1.0]; 3]);
+                assert_eq!(uvs[3..], [[0.0, 0.0]; 3]);
+            }
+            other => panic!("unexpected second uv set {other:?}"),
+        }
     }
 }
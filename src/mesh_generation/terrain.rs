@@ -0,0 +1,265 @@
+use std::collections::HashMap;
+
+use bevy::prelude::{Vec2, Vec3};
+
+use crate::coordinates::{Axial, Cube, Direction};
+
+use super::hex::{get_hex_point, get_hex_side_apothem, get_hex_side_normal};
+use super::submesh::{SubMesh, Triangle, Vertex, INDICES_IN_RANGE};
+
+fn average_2(a: f32, b: f32) -> f32 {
+    0.5 * (a + b)
+}
+
+fn average_3(a: f32, b: f32, c: f32) -> f32 {
+    // Summing in sorted order makes all three tiles sharing a corner compute the same height.
+    let mut heights = [a, b, c];
+    heights.sort_by(f32::total_cmp);
+    (heights[0] + heights[1] + heights[2]) / 3.0
+}
+
+fn create_flat_shaded(size: f32, faces: Vec<[Vec3; 3]>) -> SubMesh {
+    let mut vertices = Vec::with_capacity(3 * faces.len());
+    let mut triangles = Vec::with_capacity(faces.len());
+
+    for mut corners in faces {
+        let [a, b, c] = corners;
+        // Faces collapse to a line where the solid part shrinks to a point, they add nothing to
+        // the surface and have no normal.
+        let mut normal = match (b - a).cross(c - a).try_normalize() {
+            Some(normal) => normal,
+            None => continue,
+        };
+
+        if normal.y < 0.0 {
+            corners.swap(1, 2);
+            normal = -normal;
+        }
+
+        let offset = vertices.len() as u32;
+        vertices.extend(corners.into_iter().map(|position| {
+            let uv = Vec2::new(position.x, position.z) / (2.0 * size) + Vec2::splat(0.5);
+            Vertex::new(position, normal, uv)
+        }));
+        triangles.push(Triangle::new(offset, offset + 1, offset + 2));
+    }
+
+    SubMesh::new(vertices, triangles).expect(INDICES_IN_RANGE)
+}
+
+/// Hex tile for smoothly blended terrain. The inner `solid_factor` part of the hex is flat at
+/// `elevation`. Between that and the hex border, each side slopes towards the average of this
+/// tile and the neighbour in that direction, and each corner towards the average of the three
+/// tiles meeting there. Missing neighbours are treated as having the same elevation.
+///
+/// Neighbouring tiles compute identical border heights, so tiles placed at their `Axial`
+/// positions form a closed surface as long as `size` matches the layout spacing.
+pub fn create_blended_hex<F>(
+    size: f32,
+    solid_factor: f32,
+    elevation: f32,
+    neighbour_elevation: F,
+) -> SubMesh
+where
+    F: Fn(Direction) -> Option<f32>,
+{
+    let solid_factor = solid_factor.clamp(0.0, 1.0);
+    let bridge_width = (1.0 - solid_factor) * get_hex_side_apothem(size);
+
+    let side_heights: Vec<f32> = (0..6)
+        .map(|side| neighbour_elevation(Direction::from_side_index(side)).unwrap_or(elevation))
+        .collect();
+    let edge_height = |side: u8| average_2(elevation, side_heights[side as usize % 6]);
+    let corner_height = |corner: u8| {
+        let previous_side = (corner + 5) % 6;
+        average_3(
+            elevation,
+            side_heights[previous_side as usize],
+            side_heights[corner as usize % 6],
+        )
+    };
+
+    let at = |point: Vec3, height: f32| point + height * Vec3::Y;
+    let inner_corner = |corner: u8| at(get_hex_point(corner, solid_factor * size), elevation);
+    let bridge_point = |side: u8, corner: u8| {
+        at(
+            get_hex_point(corner, solid_factor * size) + bridge_width * get_hex_side_normal(side),
+            edge_height(side),
+        )
+    };
+    let outer_corner = |corner: u8| at(get_hex_point(corner, size), corner_height(corner));
+
+    let mut faces = vec![];
+
+    for side in 0..6u8 {
+        let (first, second) = (side, (side + 1) % 6);
+        let centre = elevation * Vec3::Y;
+
+        faces.push([centre, inner_corner(second), inner_corner(first)]);
+
+        if solid_factor >= 1.0 {
+            continue;
+        }
+
+        // Edge bridge towards the neighbour on this side.
+        let bridge = [
+            inner_corner(first),
+            inner_corner(second),
+            bridge_point(side, second),
+            bridge_point(side, first),
+        ];
+        faces.push([bridge[0], bridge[1], bridge[2]]);
+        faces.push([bridge[0], bridge[2], bridge[3]]);
+
+        // Corner between this side's bridge and the previous one.
+        let previous_side = (side + 5) % 6;
+        let inner = inner_corner(first);
+        let outer = outer_corner(first);
+        let from_previous = bridge_point(previous_side, first);
+        let from_current = bridge_point(side, first);
+
+        // Split the corner quad along the diagonal with the smaller height difference, which
+        // avoids folds when one neighbour is much higher than the other.
+        if (from_previous.y - from_current.y).abs() < (inner.y - outer.y).abs() {
+            faces.push([from_previous, from_current, inner]);
+            faces.push([from_previous, outer, from_current]);
+        } else {
+            faces.push([inner, from_previous, outer]);
+            faces.push([inner, outer, from_current]);
+        }
+    }
+
+    create_flat_shaded(size, faces)
+}
+
+/// Builds one mesh for a whole map of blended hexes, each placed at the world position of its
+/// coordinate.
+pub fn create_blended_terrain(
+    size: f32,
+    solid_factor: f32,
+    elevations: &HashMap<Cube, f32>,
+) -> SubMesh {
+    elevations
+        .iter()
+        .map(|(cube, elevation)| {
+            let neighbour_elevation =
+                |direction: Direction| elevations.get(&(*cube + direction)).copied();
+
+            create_blended_hex(size, solid_factor, *elevation, neighbour_elevation)
+                .translate(Axial::from(*cube).into())
+                .expect("tile positions are finite")
+        })
+        .fold(SubMesh::empty(), SubMesh::merge)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn world_positions(mesh: &SubMesh, origin: Vec3) -> Vec<Vec3> {
+        mesh.vertices()
+            .iter()
+            .map(|v| v.position + origin)
+            .collect()
+    }
+
+    #[test]
+    fn flat_neighbourhood_gives_flat_hex() {
+        let mesh = create_blended_hex(0.5, 0.75, 1.0, |_| Some(1.0));
+
+        assert_eq!(mesh.triangles().len(), 6 * 5);
+        for vertex in mesh.vertices() {
+            assert!((vertex.position.y - 1.0).abs() < 1e-6);
+            assert!(vertex.normal.abs_diff_eq(Vec3::Y, 1e-6));
+        }
+    }
+
+    #[test]
+    fn solid_hex_has_no_blend_region() {
+        let mesh = create_blended_hex(0.5, 1.0, 0.0, |_| Some(1.0));
+
+        assert_eq!(mesh.triangles().len(), 6);
+    }
+
+    #[test]
+    fn fully_blended_hex_has_no_degenerate_faces() {
+        let mesh = create_blended_hex(0.5, 0.0, 0.0, |direction| match direction {
+            Direction::E => Some(1.0),
+            _ => Some(0.5),
+        });
+
+        assert!(mesh.validate().is_ok());
+        for vertex in mesh.vertices() {
+            assert!(vertex.normal.is_finite());
+            assert!(vertex.normal.y > 0.0);
+        }
+    }
+
+    #[test]
+    fn border_blends_towards_neighbours() {
+        let mesh = create_blended_hex(0.5, 0.5, 0.0, |direction| match direction {
+            Direction::E => Some(1.0),
+            _ => None,
+        });
+
+        let east_border: Vec<_> = mesh
+            .vertices()
+            .iter()
+            .filter(|v| (v.position.x - get_hex_side_apothem(0.5)).abs() < 1e-5)
+            .collect();
+
+        assert!(!east_border.is_empty());
+        assert!(east_border
+            .iter()
+            .all(|v| (v.position.y - 0.5).abs() < 1e-6 || (v.position.y - 1.0 / 3.0).abs() < 1e-6));
+        assert!(mesh.vertices().iter().all(|v| v.normal.y > 0.0));
+    }
+
+    #[test]
+    fn neighbouring_tiles_share_their_border() {
+        use strum::IntoEnumIterator;
+
+        let mut elevations = HashMap::new();
+        elevations.insert(Cube::origin(), 0.0);
+        for (i, neighbour) in Cube::origin().neighbours().enumerate() {
+            elevations.insert(neighbour, i as f32 * 0.3);
+        }
+
+        let tile_positions = |cube: Cube| {
+            let neighbour_elevation =
+                |direction: Direction| elevations.get(&(cube + direction)).copied();
+            let mesh = create_blended_hex(0.5, 0.6, elevations[&cube], neighbour_elevation);
+            world_positions(&mesh, Axial::from(cube).into())
+        };
+
+        let centre = tile_positions(Cube::origin());
+
+        for direction in Direction::iter() {
+            let normal = get_hex_side_normal(direction.side_index());
+            let neighbour = tile_positions(Cube::origin() + direction);
+
+            let on_side: Vec<_> = centre
+                .iter()
+                .filter(|p| (p.dot(normal) - get_hex_side_apothem(0.5)).abs() < 1e-5)
+                .collect();
+
+            // Two corners and two bridge points lie on every side.
+            assert!(on_side.len() >= 4);
+            for p in on_side {
+                assert!(neighbour.iter().any(|q| p.abs_diff_eq(*q, 1e-5)));
+            }
+        }
+    }
+
+    #[test]
+    fn terrain_contains_all_tiles() {
+        let elevations: HashMap<_, _> = std::iter::once(Cube::origin())
+            .chain(Cube::origin().neighbours())
+            .map(|cube| (cube, 0.0))
+            .collect();
+
+        let terrain = create_blended_terrain(0.5, 0.75, &elevations);
+
+        assert_eq!(terrain.triangles().len(), 7 * 6 * 5);
+    }
+}